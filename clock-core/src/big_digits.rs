@@ -0,0 +1,311 @@
+//! Two-row "big" digits for 16x2 character LCDs, built from eight custom glyphs
+//! (stored in the HD44780's CGRAM, and shown using character codes 0-7).
+//!
+//! Each digit is two cells wide. Glyphs are made of 2 pixel strokes along the outer edges of
+//! the digit, and bars along the top/bottom of each cell (so the middle bar is split across rows):
+//!
+//! ```text
+//! 0: ┌┐  1:  │  2: =]  3: =]  4: └┘  5: [=  6: [=  7: ┌┐  8: []  9: []
+//!    └┘      │     [=     =]      │     =]     []      │     []     =]
+//! ```
+use crate::{DISPLAY_COLUMNS, SECS_TO_MILLIS};
+
+const TOP_LEFT: u8 = 0;
+const TOP_RIGHT: u8 = 1;
+const BOTTOM_LEFT: u8 = 2;
+const BOTTOM_RIGHT: u8 = 3;
+const LEFT_BRACKET: u8 = 4;
+const RIGHT_BRACKET: u8 = 5;
+const BARS: u8 = 6;
+const RIGHT_STROKE: u8 = 7;
+const BLANK: u8 = b' ';
+/// Middle dot (in the HD44780's standard A00 character ROM), shown on both rows as a colon.
+const DOT: u8 = 0xA5;
+const MINS_TO_SECS: i32 = 60;
+
+/// Pixel rows (5 bits wide, top to bottom) of each custom glyph, by character code.
+pub const GLYPHS: [[u8; 8]; 8] = [
+    [0x1F, 0x1F, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18], // top left corner
+    [0x1F, 0x1F, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03], // top right corner
+    [0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x1F, 0x1F], // bottom left corner
+    [0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x1F, 0x1F], // bottom right corner
+    [0x1F, 0x1F, 0x18, 0x18, 0x18, 0x18, 0x1F, 0x1F], // left bracket
+    [0x1F, 0x1F, 0x03, 0x03, 0x03, 0x03, 0x1F, 0x1F], // right bracket
+    [0x1F, 0x1F, 0x00, 0x00, 0x00, 0x00, 0x1F, 0x1F], // top and bottom bars
+    [0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03, 0x03], // right stroke
+];
+
+/// Cells (top row, bottom row) of each digit.
+const DIGITS: [[[u8; 2]; 2]; 10] = [
+    [[TOP_LEFT, TOP_RIGHT], [BOTTOM_LEFT, BOTTOM_RIGHT]],
+    [[BLANK, RIGHT_STROKE], [BLANK, RIGHT_STROKE]],
+    [[BARS, RIGHT_BRACKET], [LEFT_BRACKET, BARS]],
+    [[BARS, RIGHT_BRACKET], [BARS, RIGHT_BRACKET]],
+    [[BOTTOM_LEFT, BOTTOM_RIGHT], [BLANK, RIGHT_STROKE]],
+    [[LEFT_BRACKET, BARS], [BARS, RIGHT_BRACKET]],
+    [[LEFT_BRACKET, BARS], [LEFT_BRACKET, RIGHT_BRACKET]],
+    [[TOP_LEFT, TOP_RIGHT], [BLANK, RIGHT_STROKE]],
+    [[LEFT_BRACKET, RIGHT_BRACKET], [LEFT_BRACKET, RIGHT_BRACKET]],
+    [[LEFT_BRACKET, RIGHT_BRACKET], [BARS, RIGHT_BRACKET]],
+];
+
+/// Returns the LCD contents (character codes of both rows) showing players' times in big digits,
+/// red on the left and blue on the right. Each side (up to 7 cells) shows:
+/// * M:SS in big digits, when under 10 minutes
+/// * MM in big digits and seconds in normal digits on the bottom row, when under 100 minutes
+/// * [S]S.s in big digits, when under the given threshold (see [`crate::Settings::tenths_secs`])
+///
+/// Returns None if either time can't be shown (i.e. negative or 100 minutes or more).
+pub fn compose(
+    red_millis: i32,
+    blue_millis: i32,
+    tenths_millis: i32,
+) -> Option<[[u8; DISPLAY_COLUMNS]; 2]> {
+    let mut frame = [[BLANK; DISPLAY_COLUMNS]; 2];
+    let red = side(red_millis, tenths_millis)?;
+    let blue = side(blue_millis, tenths_millis)?;
+    let (red_len, blue_len) = (red.len, blue.len);
+    for (row, (red, blue)) in frame.iter_mut().zip(red.cells.iter().zip(&blue.cells)) {
+        row[..red_len].copy_from_slice(&red[..red_len]);
+        row[DISPLAY_COLUMNS - blue_len..].copy_from_slice(&blue[..blue_len]);
+    }
+    Some(frame)
+}
+
+/// One player's time, as cells of both rows.
+struct Side {
+    cells: [[u8; 7]; 2],
+    len: usize,
+}
+
+impl Side {
+    fn push_digit(&mut self, digit: i32) {
+        for (row, cells) in DIGITS[digit as usize].iter().enumerate() {
+            self.cells[row][self.len..self.len + 2].copy_from_slice(cells);
+        }
+        self.len += 2;
+    }
+
+    fn push_cells(&mut self, top: u8, bottom: u8) {
+        self.cells[0][self.len] = top;
+        self.cells[1][self.len] = bottom;
+        self.len += 1;
+    }
+}
+
+fn side(millis: i32, tenths_millis: i32) -> Option<Side> {
+    let mut side = Side {
+        cells: [[BLANK; 7]; 2],
+        len: 0,
+    };
+    let secs = millis / SECS_TO_MILLIS;
+    let (mins, secs) = (secs / MINS_TO_SECS, secs % MINS_TO_SECS);
+    match millis {
+        ..=-1 => return None,
+        _ if millis < tenths_millis => {
+            if secs >= 10 {
+                side.push_digit(secs / 10);
+            }
+            side.push_digit(secs % 10);
+            side.push_cells(BLANK, b'.');
+            side.push_digit(millis % SECS_TO_MILLIS / 100);
+        }
+        _ if mins < 10 => {
+            side.push_digit(mins);
+            side.push_cells(DOT, DOT);
+            side.push_digit(secs / 10);
+            side.push_digit(secs % 10);
+        }
+        _ if mins < 100 => {
+            side.push_digit(mins / 10);
+            side.push_digit(mins % 10);
+            side.push_cells(DOT, DOT);
+            side.push_cells(BLANK, b'0' + (secs / 10) as u8);
+            side.push_cells(BLANK, b'0' + (secs % 10) as u8);
+        }
+        _ => return None,
+    }
+    Some(side)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TENTHS: i32 = 10 * SECS_TO_MILLIS;
+
+    /// Returns the pixels of the given frame as text ('#' lit, '.' unlit; ROM characters as is),
+    /// with one line per pixel row and cells separated by spaces.
+    fn pixels(frame: &[[u8; DISPLAY_COLUMNS]; 2], columns: core::ops::Range<usize>) -> Vec<String> {
+        let cell_line = |code: u8, y: usize| -> String {
+            match code {
+                0..=7 => (0..5)
+                    .map(|x| match GLYPHS[code as usize][y] & (0x10 >> x) {
+                        0 => '.',
+                        _ => '#',
+                    })
+                    .collect(),
+                _ => (code as char).to_string().repeat(5),
+            }
+        };
+        frame
+            .iter()
+            .flat_map(|row| {
+                let cells = &row[columns.clone()];
+                (0..8).map(move |y| {
+                    let line: Vec<String> = cells.iter().map(|&code| cell_line(code, y)).collect();
+                    line.join(" ")
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_digit_uses_only_custom_glyphs_or_blanks() {
+        for digit in DIGITS.iter().flatten().flatten() {
+            assert!(*digit < 8 || *digit == BLANK);
+        }
+    }
+
+    #[test]
+    fn digits_are_drawn_from_glyphs() {
+        let frame = compose(8 * 60_000, 1_000, TENTHS).unwrap();
+        assert_eq!(
+            pixels(&frame, 0..2),
+            [
+                "##### #####",
+                "##### #####",
+                "##... ...##",
+                "##... ...##",
+                "##... ...##",
+                "##... ...##",
+                "##### #####",
+                "##### #####",
+                "##### #####",
+                "##### #####",
+                "##... ...##",
+                "##... ...##",
+                "##... ...##",
+                "##... ...##",
+                "##### #####",
+                "##### #####",
+            ]
+        );
+        let frame = compose(60_000, 1_000, TENTHS).unwrap();
+        assert!(pixels(&frame, 0..2)
+            .iter()
+            .all(|line| line == "      ...##"));
+    }
+
+    #[test]
+    fn minutes_and_seconds_under_ten_minutes() {
+        let frame = compose(9 * 60_000 + 58_999, 5 * 60_000, TENTHS).unwrap();
+        let [top, bottom] = frame;
+        // red: 9:58 on the left
+        assert_eq!(
+            top[..7],
+            [
+                LEFT_BRACKET,
+                RIGHT_BRACKET,
+                DOT,
+                LEFT_BRACKET,
+                BARS,
+                LEFT_BRACKET,
+                RIGHT_BRACKET
+            ]
+        );
+        assert_eq!(
+            bottom[..7],
+            [
+                BARS,
+                RIGHT_BRACKET,
+                DOT,
+                BARS,
+                RIGHT_BRACKET,
+                LEFT_BRACKET,
+                RIGHT_BRACKET
+            ]
+        );
+        // blue: 5:00 on the right
+        assert_eq!(
+            top[9..],
+            [
+                LEFT_BRACKET,
+                BARS,
+                DOT,
+                TOP_LEFT,
+                TOP_RIGHT,
+                TOP_LEFT,
+                TOP_RIGHT
+            ]
+        );
+        assert_eq!(top[7..9], [BLANK, BLANK]);
+    }
+
+    #[test]
+    fn small_seconds_from_ten_minutes() {
+        let [top, bottom] = compose(12 * 60_000 + 34_000, 90 * 60_000, TENTHS).unwrap();
+        assert_eq!(top[4..7], [DOT, BLANK, BLANK]);
+        assert_eq!(bottom[4..7], [DOT, b'3', b'4']);
+        assert_eq!(bottom[14..], *b"00");
+    }
+
+    #[test]
+    fn tenths_under_ten_seconds() {
+        let [top, bottom] = compose(9_400, 60_000, TENTHS).unwrap();
+        assert_eq!(
+            top[..5],
+            [
+                LEFT_BRACKET,
+                RIGHT_BRACKET,
+                BLANK,
+                BOTTOM_LEFT,
+                BOTTOM_RIGHT
+            ]
+        );
+        assert_eq!(
+            bottom[..5],
+            [BARS, RIGHT_BRACKET, b'.', BLANK, RIGHT_STROKE]
+        );
+        // from the same threshold as the text display
+        let [_, bottom] = compose(10_000, 60_000, TENTHS).unwrap();
+        assert!(!bottom[..7].contains(&b'.'));
+    }
+
+    #[test]
+    fn tenths_under_twenty_seconds() {
+        let [top, bottom] = compose(19_400, 60_000, 20_000).unwrap();
+        assert_eq!(
+            top[..7],
+            [
+                BLANK,
+                RIGHT_STROKE,
+                LEFT_BRACKET,
+                RIGHT_BRACKET,
+                BLANK,
+                BOTTOM_LEFT,
+                BOTTOM_RIGHT
+            ]
+        );
+        assert_eq!(
+            bottom[..7],
+            [
+                BLANK,
+                RIGHT_STROKE,
+                BARS,
+                RIGHT_BRACKET,
+                b'.',
+                BLANK,
+                RIGHT_STROKE
+            ]
+        );
+        let [_, bottom] = compose(20_000, 60_000, 20_000).unwrap();
+        assert!(!bottom[..7].contains(&b'.'));
+    }
+
+    #[test]
+    fn unrepresentable_times_are_rejected() {
+        assert_eq!(compose(-1, 60_000, TENTHS), None);
+        assert_eq!(compose(60_000, 100 * 60_000, TENTHS), None);
+    }
+}
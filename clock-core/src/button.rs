@@ -0,0 +1,93 @@
+use crate::{ButtonEvent, Color};
+
+/// Time a button must be held down to register as held (repeated for as long as it stays down).
+pub const HOLD_MILLIS: u64 = 1000;
+
+/// Tracks a single button's state, turning its edges into button events:
+/// * pressed: button released before HOLD_MILLIS
+/// * held: button down for HOLD_MILLIS
+/// * repeated: button still down, every HOLD_MILLIS after that until it is released
+pub struct Button {
+    color: Color,
+    next_hold: Option<u64>,
+    held: bool,
+}
+
+impl Button {
+    pub const fn new(color: Color) -> Button {
+        Button {
+            color,
+            next_hold: None,
+            held: false,
+        }
+    }
+
+    /// Register the button being pushed down.
+    pub fn down(&mut self, now: u64) {
+        self.next_hold = Some(now + HOLD_MILLIS);
+        self.held = false;
+    }
+
+    /// Register the button being released.
+    /// Returns a press, unless the button has already registered as held.
+    pub fn up(&mut self) -> Option<ButtonEvent> {
+        match self.next_hold.take() {
+            Some(_) if !self.held => Some(ButtonEvent::Pressed(self.color)),
+            _ => None,
+        }
+    }
+
+    /// Returns the time at which the button will next register as held, if it is down.
+    pub fn next_hold(&self) -> Option<u64> {
+        self.next_hold
+    }
+
+    /// Returns a hold (or its repeat) if the button has been down long enough
+    /// (see [`Button::next_hold`]).
+    pub fn poll(&mut self, now: u64) -> Option<ButtonEvent> {
+        match self.next_hold {
+            Some(next_hold) if now >= next_hold => {
+                self.next_hold = Some(next_hold + HOLD_MILLIS);
+                let event = if self.held {
+                    ButtonEvent::Repeated(self.color)
+                } else {
+                    ButtonEvent::Held(self.color)
+                };
+                self.held = true;
+                Some(event)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn release_before_hold_is_press() {
+        let mut button = Button::new(Color::Red);
+        button.down(0);
+        assert_eq!(button.poll(999), None);
+        assert_eq!(button.up(), Some(ButtonEvent::Pressed(Color::Red)));
+        assert_eq!(button.next_hold(), None);
+    }
+
+    #[test]
+    fn hold_repeats_until_release() {
+        let mut button = Button::new(Color::Blue);
+        button.down(100);
+        assert_eq!(button.poll(1_100), Some(ButtonEvent::Held(Color::Blue)));
+        assert_eq!(button.poll(1_500), None);
+        assert_eq!(button.poll(2_100), Some(ButtonEvent::Repeated(Color::Blue)));
+        assert_eq!(button.up(), None);
+        assert_eq!(button.poll(3_100), None);
+    }
+
+    #[test]
+    fn release_while_up_is_ignored() {
+        let mut button = Button::new(Color::Yellow);
+        assert_eq!(button.up(), None);
+    }
+}
@@ -0,0 +1,205 @@
+//! Driver for a 16x2 character LCD (HD44780) via an I2C backpack (PCF8574), as used by the
+//! firmware (see [`CharLcd`]).
+
+use crate::display::CharacterLcd;
+
+// HD44780 instructions (see the datasheet's instruction table)
+const CLEAR_DISPLAY: u8 = 0x01;
+const ENTRY_MODE_INCREMENT: u8 = 0x06;
+const DISPLAY_ON: u8 = 0x0C;
+const FUNCTION_SET_4BIT_2_LINES: u8 = 0x28;
+const SET_CGRAM_ADDRESS: u8 = 0x40;
+const SET_DDRAM_ADDRESS: u8 = 0x80;
+/// DDRAM address of the start of the LCD's second row.
+const SECOND_ROW_ADDRESS: u8 = 0x40;
+/// Time for the LCD to power up before it can be initialised.
+const POWER_ON_MICROS: u32 = 50_000;
+/// Time taken by the LCD to execute each of the three resets to 8-bit mode while initialising.
+const RESET_MICROS: [u32; 3] = [4_100, 100, INSTRUCTION_MICROS];
+/// Time taken by the LCD to clear itself.
+const CLEAR_MICROS: u32 = 2_000;
+/// Time taken by the LCD to execute other instructions (or write a character).
+const INSTRUCTION_MICROS: u32 = 50;
+
+// I2C backpack (PCF8574) outputs: the LCD's control lines, backlight and upper 4 data lines
+const REGISTER_SELECT: u8 = 0x01;
+const ENABLE: u8 = 0x04;
+const BACKLIGHT: u8 = 0x08;
+
+/// Writes to an I2C bus (e.g. the firmware's blocking I2C peripheral), as used by [`CharLcd`].
+pub trait I2cBus {
+    type Error;
+
+    /// Write the given bytes to the device at the given (7-bit) address.
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error>;
+
+    /// Wait (blocking) for the given time, e.g. for the LCD to execute an instruction.
+    fn delay_micros(&mut self, micros: u32);
+}
+
+/// 16x2 character LCD (HD44780), via an I2C backpack (PCF8574) at the given address.
+/// Wrap in a [`crate::ShadowLcd`] to display the clock.
+///
+/// The backpack is driven directly, as the `hd44780-driver` crate can neither define the custom
+/// glyphs used for big digits (in the LCD's CGRAM) nor switch the backlight off.
+pub struct CharLcd<B: I2cBus> {
+    bus: B,
+    address: u8,
+    /// Backlight bit, sent along with every nibble.
+    backlight: u8,
+}
+
+impl<B: I2cBus> CharLcd<B> {
+    /// Initialises the LCD (4-bit bus, two rows, no cursor, backlight on) and clears it.
+    pub fn new(bus: B, address: u8) -> Result<CharLcd<B>, B::Error> {
+        let mut lcd = CharLcd {
+            bus,
+            address,
+            backlight: BACKLIGHT,
+        };
+        // initialising by instruction (see the datasheet): once powered up, reset to 8-bit mode
+        // (nibble 3, three times), then switch to 4-bit mode (nibble 2)
+        lcd.bus.delay_micros(POWER_ON_MICROS);
+        for micros in RESET_MICROS {
+            lcd.write_nibble(0x30, 0)?;
+            lcd.bus.delay_micros(micros);
+        }
+        lcd.write_nibble(0x20, 0)?;
+        lcd.bus.delay_micros(INSTRUCTION_MICROS);
+        let setup = [
+            FUNCTION_SET_4BIT_2_LINES,
+            DISPLAY_ON,
+            ENTRY_MODE_INCREMENT,
+            CLEAR_DISPLAY,
+        ];
+        for instruction in setup {
+            lcd.instruction(instruction)?;
+        }
+        lcd.bus.delay_micros(CLEAR_MICROS);
+        Ok(lcd)
+    }
+
+    fn instruction(&mut self, instruction: u8) -> Result<(), B::Error> {
+        self.write(instruction, 0)
+    }
+
+    /// Send the given byte as two nibbles (high first).
+    fn write(&mut self, byte: u8, register_select: u8) -> Result<(), B::Error> {
+        self.write_nibble(byte & 0xF0, register_select)?;
+        self.write_nibble(byte << 4, register_select)?;
+        self.bus.delay_micros(INSTRUCTION_MICROS);
+        Ok(())
+    }
+
+    /// Send the given nibble (the upper 4 bits), latched by pulsing the enable line.
+    fn write_nibble(&mut self, nibble: u8, register_select: u8) -> Result<(), B::Error> {
+        let outputs = nibble | register_select | self.backlight;
+        self.bus.write(self.address, &[outputs | ENABLE, outputs])
+    }
+}
+
+impl<B: I2cBus> CharacterLcd for CharLcd<B> {
+    type Error = B::Error;
+
+    fn set_cursor(&mut self, row: u8, column: u8) -> Result<(), B::Error> {
+        self.instruction(SET_DDRAM_ADDRESS | (row * SECOND_ROW_ADDRESS + column))
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), B::Error> {
+        for byte in bytes {
+            self.write(*byte, REGISTER_SELECT)?;
+        }
+        Ok(())
+    }
+
+    fn define_glyph(&mut self, code: u8, rows: &[u8; 8]) -> Result<(), B::Error> {
+        self.instruction(SET_CGRAM_ADDRESS | (code << 3))?;
+        self.write_bytes(rows)
+    }
+
+    fn set_backlight(&mut self, on: bool) -> Result<(), B::Error> {
+        self.backlight = if on { BACKLIGHT } else { 0 };
+        self.bus.write(self.address, &[self.backlight])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::{ClockDisplay, ShadowLcd};
+
+    const ADDRESS: u8 = 0x27;
+
+    /// I2C bus which records the raw bytes of every write (shared, so that they can be checked
+    /// while the LCD owns the bus), and the total time waited.
+    #[derive(Clone, Default)]
+    struct RecordingBus {
+        writes: Rc<RefCell<Vec<Vec<u8>>>>,
+        waited_micros: Rc<RefCell<u32>>,
+    }
+
+    impl RecordingBus {
+        /// Returns the writes since the last call.
+        fn take(&self) -> Vec<Vec<u8>> {
+            self.writes.take()
+        }
+    }
+
+    impl I2cBus for RecordingBus {
+        type Error = core::convert::Infallible;
+
+        fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+            assert_eq!(address, ADDRESS);
+            self.writes.borrow_mut().push(bytes.to_vec());
+            Ok(())
+        }
+
+        fn delay_micros(&mut self, micros: u32) {
+            *self.waited_micros.borrow_mut() += micros;
+        }
+    }
+
+    #[test]
+    fn initialisation_switches_to_4_bit_mode() {
+        let bus = RecordingBus::default();
+        CharLcd::new(bus.clone(), ADDRESS).unwrap();
+        let writes = bus.take();
+        // nibble 3 three times, then 2, each strobed with the enable line (backlight on)
+        assert!(writes[..3].iter().all(|bytes| *bytes == [0x3C, 0x38]));
+        assert_eq!(writes[3], [0x2C, 0x28]);
+        // then instructions as two nibbles each, ending with clearing the display
+        assert_eq!(writes[4..6], [vec![0x2C, 0x28], vec![0x8C, 0x88]]);
+        assert_eq!(writes.len(), 4 + 2 * 4);
+        assert_eq!(writes[10..], [vec![0x0C, 0x08], vec![0x1C, 0x18]]);
+        assert!(*bus.waited_micros.borrow() > POWER_ON_MICROS + CLEAR_MICROS);
+    }
+
+    #[test]
+    fn characters_are_sent_as_nibbles_with_the_backlight_bit() {
+        let bus = RecordingBus::default();
+        let mut lcd = CharLcd::new(bus.clone(), ADDRESS).unwrap();
+        bus.take();
+        lcd.write_bytes(b"A").unwrap();
+        assert_eq!(bus.take(), [[0x4D, 0x49], [0x1D, 0x19]]);
+        lcd.set_backlight(false).unwrap();
+        lcd.set_cursor(1, 2).unwrap();
+        assert_eq!(bus.take(), [vec![0x00], vec![0xC4, 0xC0], vec![0x24, 0x20]]);
+    }
+
+    #[test]
+    fn repeated_frame_writes_no_bytes() {
+        let bus = RecordingBus::default();
+        let mut lcd = ShadowLcd::new(CharLcd::new(bus.clone(), ADDRESS).unwrap());
+        lcd.show("  Time control  ", "      Incr      ").unwrap();
+        assert!(!bus.take().is_empty());
+        lcd.show("  Time control  ", "      Incr      ").unwrap();
+        assert_eq!(bus.take(), Vec::<Vec<u8>>::new());
+        assert!(lcd.show_times(5 * 60_000, 5 * 60_000, 10_000).unwrap());
+        assert!(!bus.take().is_empty());
+        assert!(lcd.show_times(5 * 60_000, 5 * 60_000, 10_000).unwrap());
+        assert_eq!(bus.take(), Vec::<Vec<u8>>::new());
+    }
+}
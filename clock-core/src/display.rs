@@ -0,0 +1,446 @@
+use core::convert::Infallible;
+
+use crate::big_digits::{self, GLYPHS};
+
+/// Number of characters per row of the clock's text (as on a 16x2 character LCD).
+pub const DISPLAY_COLUMNS: usize = 16;
+
+/// A display showing the clock's two rows of text (see [`crate::Game::display_text`]).
+pub trait ClockDisplay {
+    type Error;
+
+    /// Show the given header (top row) and status (bottom row), replacing any previous text.
+    fn show(&mut self, header: &str, status: &str) -> Result<(), Self::Error>;
+
+    /// Show only the players' times (in millis), filling the display (e.g. in big digits),
+    /// in tenths of a second below the given threshold.
+    /// Returns false, without changing the display, if it can't show the times on their own.
+    fn show_times(
+        &mut self,
+        red_millis: i32,
+        blue_millis: i32,
+        tenths_millis: i32,
+    ) -> Result<bool, Self::Error> {
+        let _ = (red_millis, blue_millis, tenths_millis);
+        Ok(false)
+    }
+
+    /// Switch the display's backlight on or off (or dim it). Displays without one ignore this.
+    fn set_backlight(&mut self, on: bool) -> Result<(), Self::Error> {
+        let _ = on;
+        Ok(())
+    }
+}
+
+/// In-memory 16x2 character display, e.g. for inspecting rendered output in tests.
+/// Like a character LCD, text beyond the last column is cut off.
+pub struct FrameBuffer {
+    rows: [[u8; DISPLAY_COLUMNS]; 2],
+}
+
+impl Default for FrameBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameBuffer {
+    pub const fn new() -> FrameBuffer {
+        FrameBuffer {
+            rows: [[b' '; DISPLAY_COLUMNS]; 2],
+        }
+    }
+
+    /// Returns the text currently shown on the given row (0: header, 1: status).
+    pub fn row(&self, row: usize) -> &str {
+        core::str::from_utf8(&self.rows[row]).unwrap_or("")
+    }
+}
+
+impl ClockDisplay for FrameBuffer {
+    type Error = Infallible;
+
+    fn show(&mut self, header: &str, status: &str) -> Result<(), Infallible> {
+        for (row, text) in self.rows.iter_mut().zip([header, status]) {
+            row.fill(b' ');
+            for (cell, c) in row.iter_mut().zip(text.chars()) {
+                *cell = if c.is_ascii() { c as u8 } else { b'?' };
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Low-level operations of a 16x2 character LCD (e.g. an HD44780 driver), as used by [`ShadowLcd`].
+pub trait CharacterLcd {
+    type Error;
+
+    /// Move the cursor to the given row (0: header, 1: status) and column.
+    fn set_cursor(&mut self, row: u8, column: u8) -> Result<(), Self::Error>;
+
+    /// Write the given character codes at the cursor, moving the cursor past them.
+    /// Codes 0-7 show the custom glyphs defined with [`CharacterLcd::define_glyph`].
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
+
+    /// Define the custom glyph (5x8 pixels, as one byte per row) shown for the given code (0-7).
+    fn define_glyph(&mut self, code: u8, rows: &[u8; 8]) -> Result<(), Self::Error>;
+
+    /// Switch the LCD's backlight on or off. LCDs without a switchable backlight ignore this.
+    fn set_backlight(&mut self, on: bool) -> Result<(), Self::Error> {
+        let _ = on;
+        Ok(())
+    }
+}
+
+/// Character LCD which keeps a shadow copy of its contents, so that only the cells which change
+/// are sent to it. This avoids the flicker (and bus traffic) of clearing and rewriting the whole
+/// display on every frame.
+///
+/// During play, players' times are shown in big digits (see [`crate::big_digits`]).
+pub struct ShadowLcd<L: CharacterLcd> {
+    lcd: L,
+    shadow: FrameBuffer,
+    glyphs_defined: bool,
+}
+
+impl<L: CharacterLcd> ShadowLcd<L> {
+    /// Wraps the given LCD, which must have just been cleared.
+    pub fn new(lcd: L) -> ShadowLcd<L> {
+        ShadowLcd {
+            lcd,
+            shadow: FrameBuffer::new(),
+            glyphs_defined: false,
+        }
+    }
+
+    /// Send the cells of the given frame which differ from the shadow copy.
+    fn update(&mut self, frame: FrameBuffer) -> Result<(), L::Error> {
+        for (row, (new, old)) in frame.rows.iter().zip(&self.shadow.rows).enumerate() {
+            // send each run of changed cells with a single cursor move
+            let mut column = 0;
+            while column < DISPLAY_COLUMNS {
+                if new[column] == old[column] {
+                    column += 1;
+                    continue;
+                }
+                let start = column;
+                while column < DISPLAY_COLUMNS && new[column] != old[column] {
+                    column += 1;
+                }
+                self.lcd.set_cursor(row as u8, start as u8)?;
+                self.lcd.write_bytes(&new[start..column])?;
+            }
+        }
+        self.shadow = frame;
+        Ok(())
+    }
+}
+
+impl<L: CharacterLcd> ClockDisplay for ShadowLcd<L> {
+    type Error = L::Error;
+
+    fn show(&mut self, header: &str, status: &str) -> Result<(), L::Error> {
+        let mut frame = FrameBuffer::new();
+        let Ok(()) = frame.show(header, status);
+        self.update(frame)
+    }
+
+    fn show_times(
+        &mut self,
+        red_millis: i32,
+        blue_millis: i32,
+        tenths_millis: i32,
+    ) -> Result<bool, L::Error> {
+        let Some(rows) = big_digits::compose(red_millis, blue_millis, tenths_millis) else {
+            return Ok(false);
+        };
+        if !self.glyphs_defined {
+            for (code, glyph) in GLYPHS.iter().enumerate() {
+                self.lcd.define_glyph(code as u8, glyph)?;
+            }
+            self.glyphs_defined = true;
+        }
+        self.update(FrameBuffer { rows })?;
+        Ok(true)
+    }
+
+    fn set_backlight(&mut self, on: bool) -> Result<(), L::Error> {
+        self.lcd.set_backlight(on)
+    }
+}
+
+/// Text layout for graphical (e.g. 128x64 OLED) displays using embedded-graphics.
+#[cfg(feature = "graphics")]
+pub mod graphics {
+    use embedded_graphics::mono_font::ascii::{FONT_10X20, FONT_6X10};
+    use embedded_graphics::mono_font::{MonoFont, MonoTextStyle};
+    use embedded_graphics::pixelcolor::BinaryColor;
+    use embedded_graphics::prelude::*;
+    use embedded_graphics::text::{Alignment, Baseline, Text, TextStyle, TextStyleBuilder};
+
+    use crate::DISPLAY_COLUMNS;
+
+    const HEADER_FONT: MonoFont = FONT_6X10;
+    const STATUS_FONT: MonoFont = FONT_10X20;
+
+    /// Draw the clock's header and status to the given target, replacing its contents.
+    /// The header is drawn in a small font along the top. The status is drawn in a large font
+    /// below it: as a single line if it fits, otherwise as its two halves (i.e. each player's
+    /// side of the display), falling back to the small font if even those don't fit.
+    pub fn draw_text<D>(target: &mut D, header: &str, status: &str) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        target.clear(BinaryColor::Off)?;
+        let area = target.bounding_box();
+        let width = area.size.width as i32;
+        let big_columns = (width / STATUS_FONT.character_size.width as i32) as usize;
+        let middle = area.center().y + HEADER_FONT.character_size.height as i32 / 2;
+        let top = style(Alignment::Center, Baseline::Top);
+        let center = style(Alignment::Center, Baseline::Middle);
+
+        draw_line(
+            target,
+            header.trim(),
+            HEADER_FONT,
+            Point::new(width / 2, 0),
+            top,
+        )?;
+
+        let half = status.len().min(DISPLAY_COLUMNS / 2);
+        let (left, right) = status.split_at(half);
+        let (left, right) = (left.trim(), right.trim());
+        if status.trim().len() <= big_columns {
+            let position = Point::new(width / 2, middle);
+            draw_line(target, status.trim(), STATUS_FONT, position, center)?;
+        } else if left.len().max(right.len()) <= big_columns / 2 {
+            let position = Point::new(0, middle);
+            let left_style = style(Alignment::Left, Baseline::Middle);
+            draw_line(target, left, STATUS_FONT, position, left_style)?;
+            let position = Point::new(width - 1, middle);
+            let right_style = style(Alignment::Right, Baseline::Middle);
+            draw_line(target, right, STATUS_FONT, position, right_style)?;
+        } else {
+            let position = Point::new(width / 2, middle);
+            draw_line(target, status.trim(), HEADER_FONT, position, center)?;
+        }
+        Ok(())
+    }
+
+    fn style(alignment: Alignment, baseline: Baseline) -> TextStyle {
+        TextStyleBuilder::new()
+            .alignment(alignment)
+            .baseline(baseline)
+            .build()
+    }
+
+    fn draw_line<D>(
+        target: &mut D,
+        text: &str,
+        font: MonoFont,
+        position: Point,
+        text_style: TextStyle,
+    ) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        let character_style = MonoTextStyle::new(&font, BinaryColor::On);
+        Text::with_text_style(text, position, character_style, text_style).draw(target)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_buffer_pads_and_truncates_rows() {
+        let mut frame = FrameBuffer::new();
+        frame.show("Red         Blue", "05:00").unwrap();
+        assert_eq!(frame.row(0), "Red         Blue");
+        assert_eq!(frame.row(1), "05:00           ");
+        frame.show("Red flag", "0123456789abcdefXYZ").unwrap();
+        assert_eq!(frame.row(0), "Red flag        ");
+        assert_eq!(frame.row(1), "0123456789abcdef");
+    }
+
+    /// Character LCD which records the text written at each cursor position, and counts every
+    /// call made to it (the bytes sent over the bus are checked with [`crate::CharLcd`]).
+    #[derive(Default)]
+    struct RecordingLcd {
+        cursor: (u8, u8),
+        writes: Vec<(u8, u8, String)>,
+        glyphs: Vec<u8>,
+        calls: usize,
+    }
+
+    impl CharacterLcd for RecordingLcd {
+        type Error = Infallible;
+
+        fn set_cursor(&mut self, row: u8, column: u8) -> Result<(), Infallible> {
+            self.calls += 1;
+            self.cursor = (row, column);
+            Ok(())
+        }
+
+        fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Infallible> {
+            self.calls += 1;
+            let text = String::from_utf8_lossy(bytes).to_string();
+            self.writes.push((self.cursor.0, self.cursor.1, text));
+            self.cursor.1 += bytes.len() as u8;
+            Ok(())
+        }
+
+        fn define_glyph(&mut self, code: u8, _rows: &[u8; 8]) -> Result<(), Infallible> {
+            self.calls += 1;
+            self.glyphs.push(code);
+            Ok(())
+        }
+
+        fn set_backlight(&mut self, _on: bool) -> Result<(), Infallible> {
+            self.calls += 1;
+            Ok(())
+        }
+    }
+
+    fn writes(lcd: &mut ShadowLcd<RecordingLcd>) -> Vec<(u8, u8, String)> {
+        core::mem::take(&mut lcd.lcd.writes)
+    }
+
+    #[test]
+    fn shadow_lcd_sends_only_changed_cells() {
+        let mut lcd = ShadowLcd::new(RecordingLcd::default());
+        lcd.show("Red         Blue", "10:00      10:00").unwrap();
+        assert_eq!(
+            writes(&mut lcd),
+            [
+                (0, 0, "Red".to_string()),
+                (0, 12, "Blue".to_string()),
+                (1, 0, "10:00".to_string()),
+                (1, 11, "10:00".to_string()),
+            ]
+        );
+        lcd.show("Red         Blue", "09:59      10:00").unwrap();
+        assert_eq!(
+            writes(&mut lcd),
+            [(1, 0, "09".to_string()), (1, 3, "59".to_string())]
+        );
+        lcd.show("   Red flag", "00:00").unwrap();
+        assert_eq!(
+            writes(&mut lcd),
+            [
+                (0, 0, "   Red".to_string()),
+                (0, 7, "flag".to_string()),
+                (0, 12, "    ".to_string()),
+                (1, 1, "0".to_string()),
+                (1, 3, "00".to_string()),
+                (1, 11, "     ".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn shadow_lcd_unchanged_frame_sends_nothing() {
+        let mut lcd = ShadowLcd::new(RecordingLcd::default());
+        lcd.show("  Time control  ", "      Incr      ").unwrap();
+        assert!(lcd.lcd.calls > 0);
+        lcd.lcd.calls = 0;
+        lcd.show("  Time control  ", "      Incr      ").unwrap();
+        assert_eq!(lcd.lcd.calls, 0);
+        assert!(lcd.show_times(5 * 60_000, 5 * 60_000, 10_000).unwrap());
+        lcd.lcd.calls = 0;
+        assert!(lcd.show_times(5 * 60_000, 5 * 60_000, 10_000).unwrap());
+        assert_eq!(lcd.lcd.calls, 0);
+    }
+
+    #[test]
+    fn shadow_lcd_shows_big_digits_after_defining_glyphs() {
+        let mut lcd = ShadowLcd::new(RecordingLcd::default());
+        lcd.show("Red         Blue", "05:00      05:00").unwrap();
+        writes(&mut lcd);
+        assert!(lcd
+            .show_times(5 * 60_000 + 59_000, 5 * 60_000, 10_000)
+            .unwrap());
+        assert_eq!(lcd.lcd.glyphs, [0, 1, 2, 3, 4, 5, 6, 7]);
+        assert!(!writes(&mut lcd).is_empty());
+        // glyphs are only defined once, and only changed cells are sent (5:59 -> 5:58)
+        assert!(lcd
+            .show_times(5 * 60_000 + 58_000, 5 * 60_000, 10_000)
+            .unwrap());
+        assert_eq!(lcd.lcd.glyphs.len(), 8);
+        assert_eq!(writes(&mut lcd), [(1, 5, "\u{4}".to_string())]);
+        // times which can't be shown in big digits leave the display as is
+        assert!(!lcd.show_times(-1_000, 5 * 60_000, 10_000).unwrap());
+        assert!(writes(&mut lcd).is_empty());
+    }
+
+    #[test]
+    fn other_displays_dont_show_times_alone() {
+        let mut frame = FrameBuffer::new();
+        assert!(!frame.show_times(60_000, 60_000, 10_000).unwrap());
+    }
+
+    #[cfg(feature = "graphics")]
+    mod graphics {
+        use super::super::graphics::draw_text;
+        use embedded_graphics::pixelcolor::BinaryColor;
+        use embedded_graphics::prelude::*;
+
+        /// 128x64 monochrome display buffer.
+        struct Oled([[bool; 128]; 64]);
+
+        impl OriginDimensions for Oled {
+            fn size(&self) -> Size {
+                Size::new(128, 64)
+            }
+        }
+
+        impl DrawTarget for Oled {
+            type Color = BinaryColor;
+            type Error = core::convert::Infallible;
+
+            fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+            where
+                I: IntoIterator<Item = Pixel<BinaryColor>>,
+            {
+                for Pixel(point, color) in pixels {
+                    if let (Ok(x @ 0..=127), Ok(y @ 0..=63)) =
+                        (usize::try_from(point.x), usize::try_from(point.y))
+                    {
+                        self.0[y][x] = color.is_on();
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        impl Oled {
+            /// Returns the range of columns with any pixels lit in the given range of rows.
+            fn lit_columns(&self, rows: core::ops::Range<usize>) -> Option<(usize, usize)> {
+                let lit = |x: usize| rows.clone().any(|y| self.0[y][x]);
+                Some(((0..128).find(|x| lit(*x))?, (0..128).rfind(|x| lit(*x))?))
+            }
+        }
+
+        #[test]
+        fn players_times_drawn_large_on_either_side() {
+            let mut oled = Oled([[false; 128]; 64]);
+            draw_text(&mut oled, "Red         Blue", "10:00      09:59").unwrap();
+            assert!(oled.lit_columns(0..10).is_some());
+            let (left, right) = oled.lit_columns(20..64).unwrap();
+            assert!(left < 5 && right > 122);
+            // digits in the large font are 13 pixels tall (vs. 7 in the header font)
+            let tall = (20..64).filter(|y| oled.0[*y][..64].iter().any(|lit| *lit));
+            assert!(tall.count() >= 12);
+        }
+
+        #[test]
+        fn redrawing_clears_previous_text() {
+            let mut oled = Oled([[false; 128]; 64]);
+            draw_text(&mut oled, "", "10:00      09:59").unwrap();
+            draw_text(&mut oled, "", "").unwrap();
+            assert_eq!(oled.lit_columns(0..64), None);
+        }
+    }
+}
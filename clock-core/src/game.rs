@@ -0,0 +1,1685 @@
+use core::fmt::Write;
+use heapless::{String, Vec};
+
+use crate::menu::{Menu, Outcome, Value};
+use crate::player::{formatted_setting, formatted_time, Player};
+use crate::presets::{MAX_CUSTOM_PRESETS, PRESETS};
+use crate::settings::{Settings, TimeSettings};
+use crate::sound::Sound;
+use crate::time_control::{FlagBehavior, Link, TimeControl, STAGE_PLANS};
+use crate::{ButtonEvent, ClockDisplay, Color, DISPLAY_COLUMNS, SECS_TO_MILLIS};
+
+/// Time a blinking LED (of a flagged player, or one low on time) stays lit or unlit.
+const BLINK_MILLIS: u64 = 500;
+/// Low-time warning thresholds selectable in the settings menu, in seconds (0: off).
+pub(crate) const WARNING_SECS: [u8; 5] = [0, 10, 20, 30, 60];
+const DEFAULT_WARNING_SECS: u8 = 30;
+/// Thresholds below which times are shown in tenths of a second, selectable in the settings
+/// menu, in seconds.
+pub(crate) const TENTHS_SECS: [u8; 2] = [10, 20];
+const DEFAULT_TENTHS_SECS: u8 = 10;
+
+/// Input to the game's state machine (see [`Game::handle`]).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Event {
+    Button(ButtonEvent),
+    /// Periodic timer tick (e.g. every 100 millis), used to refresh the clocks and detect flag fall.
+    Tick,
+}
+
+/// Output of the game's state machine, to be applied to the hardware by the caller.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Action {
+    /// Switch the given color's LED on (true) or off (false).
+    Led(Color, bool),
+    /// Redraw the LCD with the game's current [`Game::display_text`].
+    Display,
+    /// Persist the game's [`Game::saved_settings`] (e.g. to flash), as a game has been started
+    /// with new settings, or the custom presets, sound or backlight have changed.
+    SaveSettings,
+    /// Switch the display's backlight on (true) or off (false).
+    Backlight(bool),
+    /// Play the given sound (if the clock has a buzzer and its sound is on).
+    Sound(Sound),
+}
+
+/// Actions returned from a single call to [`Game::handle`]: at most one of each kind (and one
+/// per LED), with room to spare.
+pub type Actions = Vec<Action, 8>;
+
+/// Controls overall game (timer) state.
+/// Times (`now`) are in millis since an arbitrary fixed point in time (e.g. boot).
+pub struct Game {
+    pub(crate) phase: GameStatus,
+    pub(crate) setting: Setting,
+    /// Field of the time being edited, on the pre-game time and bonus pages.
+    pub(crate) cursor: Field,
+    pub(crate) flag_behavior: FlagBehavior,
+    pub(crate) link: Link,
+    pub(crate) red_player: Player,
+    pub(crate) blue_player: Player,
+    /// Whether the LED of a player whose flag has fallen (or who is low on time) is currently lit,
+    /// and when it last changed.
+    blink: (bool, u64),
+    pub(crate) custom_presets: Vec<TimeSettings, MAX_CUSTOM_PRESETS>,
+    pub(crate) sound: bool,
+    pub(crate) backlight: bool,
+    /// Low-time warning threshold, in seconds (0: off).
+    pub(crate) warning_secs: u8,
+    /// Threshold below which times are shown in tenths of a second, in seconds.
+    pub(crate) tenths_secs: u8,
+    /// Whether each player's time (red, blue) was below the warning threshold when last checked.
+    low_time: [bool; 2],
+    /// Settings menu, while it is open (pre-game only).
+    menu: Option<Menu>,
+    /// Settings restored when the game is reset: those of the last game started.
+    saved: Settings,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Game {
+    pub fn new() -> Game {
+        Game {
+            phase: GameStatus::PreGame,
+            setting: Setting::Preset,
+            cursor: Field::Hours,
+            flag_behavior: FlagBehavior::HardStop,
+            link: Link::Unlinked,
+            red_player: Player::new(),
+            blue_player: Player::new(),
+            blink: (false, 0),
+            custom_presets: Vec::new(),
+            sound: true,
+            backlight: true,
+            warning_secs: DEFAULT_WARNING_SECS,
+            tenths_secs: DEFAULT_TENTHS_SECS,
+            low_time: [false; 2],
+            menu: None,
+            saved: Settings {
+                time: TimeSettings {
+                    time_control: TimeControl::Increment,
+                    stage_plan: 0,
+                    players: [Player::new().settings(); 2],
+                },
+                flag_behavior: FlagBehavior::HardStop,
+                link: Link::Unlinked,
+                custom_presets: Vec::new(),
+                sound: true,
+                backlight: true,
+                warning_secs: DEFAULT_WARNING_SECS,
+                tenths_secs: DEFAULT_TENTHS_SECS,
+            },
+        }
+    }
+
+    /// Returns a new game with the given settings (e.g. those last saved), restored on reset.
+    pub fn with_settings(settings: Settings) -> Game {
+        let mut game = Game::new();
+        game.custom_presets = settings.custom_presets.clone();
+        game.saved = settings;
+        game.reset();
+        game
+    }
+
+    /// Returns the current pre-game settings.
+    pub fn settings(&self) -> Settings {
+        Settings {
+            time: self.time_settings(),
+            flag_behavior: self.flag_behavior,
+            link: self.link,
+            custom_presets: self.custom_presets.clone(),
+            sound: self.sound,
+            backlight: self.backlight,
+            warning_secs: self.warning_secs,
+            tenths_secs: self.tenths_secs,
+        }
+    }
+
+    /// Returns the settings to be persisted: those of the last game started, along with the custom
+    /// presets, sound, backlight, warning and tenths thresholds (kept as soon as they change).
+    pub fn saved_settings(&self) -> &Settings {
+        &self.saved
+    }
+
+    /// Returns the current time control (as set by a preset).
+    fn time_settings(&self) -> TimeSettings {
+        TimeSettings {
+            time_control: self.red_player.time_control,
+            stage_plan: self.red_player.stage_plan as u8,
+            players: [self.red_player.settings(), self.blue_player.settings()],
+        }
+    }
+
+    /// Returns the current game phase.
+    pub fn phase(&self) -> GameStatus {
+        self.phase
+    }
+
+    /// Returns the number of moves (turns) completed by each player (red, blue), e.g. for a
+    /// record of the game.
+    pub fn moves(&self) -> (u16, u16) {
+        (self.red_player.moves, self.blue_player.moves)
+    }
+
+    /// Returns the number of the move in progress, counting a move by each player as one
+    /// (as in chess notation, the first to move being white).
+    pub fn move_number(&self) -> u16 {
+        (self.red_player.moves + self.blue_player.moves) / 2 + 1
+    }
+
+    /// Advance the game in response to the given event, returning the LED/LCD updates to apply.
+    /// * pre-game: Red/Blue select a preset or adjust the current setting (press: 1 step, hold: 5);
+    ///   Yellow starts the game with the selected preset, or else moves on (to the next field of a
+    ///   time first); holding Yellow opens the settings menu
+    /// * paused: Red/Blue start the opponent's clock; holding Yellow resets the game
+    /// * active: Red/Blue end the player's turn; Yellow pauses; holding Yellow resets the game
+    /// * flagged: the loser's LED blinks; holding Yellow resets the game. A press made once a
+    ///   player's time has run out (before the tick detecting it) is ignored
+    ///
+    /// While the active player's time is below the warning threshold, their LED and time flash.
+    /// Sounds are played as turns switch, a player's time drops below the warning threshold,
+    /// a flag falls, or a value is confirmed in the settings menu.
+    pub fn handle(&mut self, event: Event, now: u64) -> Actions {
+        let leds = self.leds();
+        let phase = self.phase;
+        let turn = [self.red_player.is_active, self.blue_player.is_active];
+        let backlight = self.backlight;
+        let mut redraw = true;
+        let mut confirmed = false;
+        // a flag which has fallen since the last tick ends the game before the event counts
+        // (e.g. a move made too late)
+        let flagged = phase == GameStatus::Active && {
+            self.check_flag(now);
+            self.phase != GameStatus::Active
+        };
+        match (self.phase, event) {
+            _ if flagged => (),
+            (GameStatus::PreGame, Event::Button(button)) if self.menu.is_some() => {
+                confirmed = self.handle_menu(button)
+            }
+            // only the first hold of Yellow counts, e.g. so that a hold which reset the game
+            // doesn't go on to open the settings menu
+            (_, Event::Button(ButtonEvent::Repeated(Color::Yellow))) => (),
+            (GameStatus::PreGame, Event::Button(ButtonEvent::Held(Color::Yellow))) => {
+                self.menu = Some(Menu::new())
+            }
+            (_, Event::Button(ButtonEvent::Held(Color::Yellow))) => self.reset(),
+            (GameStatus::PreGame, Event::Button(ButtonEvent::Pressed(Color::Yellow))) => {
+                self.next_setting()
+            }
+            (GameStatus::PreGame, Event::Button(ButtonEvent::Pressed(color))) => {
+                self.adjust_setting(self.setting, color, 1)
+            }
+            (GameStatus::PreGame, Event::Button(ButtonEvent::Held(_)))
+                if self.setting == Setting::Preset =>
+            {
+                self.save_or_delete_preset()
+            }
+            // a hold repeating on the preset page is ignored, as it would undo the hold
+            (
+                GameStatus::PreGame,
+                Event::Button(ButtonEvent::Held(color) | ButtonEvent::Repeated(color)),
+            ) if self.setting != Setting::Preset => self.adjust_setting(self.setting, color, 5),
+            (GameStatus::Paused, Event::Button(ButtonEvent::Pressed(Color::Red))) => {
+                self.start_turn(Color::Blue, now)
+            }
+            (GameStatus::Paused, Event::Button(ButtonEvent::Pressed(Color::Blue))) => {
+                self.start_turn(Color::Red, now)
+            }
+            (GameStatus::Active, Event::Button(ButtonEvent::Pressed(Color::Yellow))) => {
+                self.pause_clocks(now);
+                self.phase = GameStatus::Paused;
+            }
+            (GameStatus::Active, Event::Button(ButtonEvent::Pressed(color))) => {
+                self.end_turn(color, now)
+            }
+            (GameStatus::Active, Event::Tick) => {
+                self.toggle_blink(now);
+            }
+            (GameStatus::Active, _) => (),
+            (GameStatus::Flagged(_), Event::Tick) => redraw = self.toggle_blink(now),
+            (_, Event::Tick) => redraw = false,
+            // e.g. turn presses once a flag has fallen, which are ignored until the game is reset
+            _ => (),
+        }
+        let switched = self.phase == GameStatus::Active
+            && [self.red_player.is_active, self.blue_player.is_active] != turn;
+        if switched {
+            // a player low on time starts their turn with their LED lit
+            self.blink = (true, now);
+        }
+        let warned = self.phase != GameStatus::PreGame
+            && self.check_low_time(now)
+            && self.phase == GameStatus::Active;
+
+        let mut actions = Actions::new();
+        for (color, (was_on, is_on)) in [Color::Red, Color::Yellow, Color::Blue]
+            .into_iter()
+            .zip(leds.into_iter().zip(self.leds()))
+        {
+            if was_on != is_on {
+                actions.push(Action::Led(color, is_on)).unwrap();
+            }
+        }
+        if redraw {
+            actions.push(Action::Display).unwrap();
+        }
+        if self.backlight != backlight {
+            actions.push(Action::Backlight(self.backlight)).unwrap();
+        }
+        let sound = if self.phase != phase && matches!(self.phase, GameStatus::Flagged(_)) {
+            Some(Sound::Flag)
+        } else if warned {
+            Some(Sound::Warning)
+        } else if confirmed {
+            Some(Sound::Confirm)
+        } else if switched {
+            Some(Sound::Click)
+        } else {
+            None
+        };
+        if let Some(sound) = sound.filter(|_| self.sound) {
+            actions.push(Action::Sound(sound)).unwrap();
+        }
+        if phase == GameStatus::PreGame {
+            let saved = if self.phase == GameStatus::Paused {
+                self.settings()
+            } else {
+                Settings {
+                    custom_presets: self.custom_presets.clone(),
+                    sound: self.sound,
+                    backlight: self.backlight,
+                    warning_secs: self.warning_secs,
+                    tenths_secs: self.tenths_secs,
+                    ..self.saved.clone()
+                }
+            };
+            if saved != self.saved {
+                self.saved = saved;
+                actions.push(Action::SaveSettings).unwrap();
+            }
+        }
+        actions
+    }
+
+    /// Returns the state of the LEDs (red, yellow, blue): lit for the active player (blinking if
+    /// they are low on time), yellow while paused, and blinking for a player whose flag has fallen.
+    fn leds(&self) -> [bool; 3] {
+        let flagged = |color| self.phase == GameStatus::Flagged(color) && self.blink.0;
+        let lit = |color| self.low_on_time() != Some(color) || self.blink.0;
+        [
+            self.red_player.is_active && lit(Color::Red) || flagged(Color::Red),
+            self.phase == GameStatus::Paused,
+            self.blue_player.is_active && lit(Color::Blue) || flagged(Color::Blue),
+        ]
+    }
+
+    /// Returns the active player, if their time is below the warning threshold
+    /// (see [`Game::check_low_time`]).
+    fn low_on_time(&self) -> Option<Color> {
+        match (self.phase, self.low_time) {
+            (GameStatus::Active, [true, _]) if self.red_player.is_active => Some(Color::Red),
+            (GameStatus::Active, [_, true]) if self.blue_player.is_active => Some(Color::Blue),
+            _ => None,
+        }
+    }
+
+    /// Toggle the blinking LED once it has been lit or unlit for BLINK_MILLIS.
+    /// Returns true if it changed.
+    fn toggle_blink(&mut self, now: u64) -> bool {
+        let toggle = now.saturating_sub(self.blink.1) >= BLINK_MILLIS;
+        if toggle {
+            self.blink = (!self.blink.0, now);
+        }
+        toggle
+    }
+
+    /// Returns the two rows of text to be shown on the LCD, showing players' status (time remaining).
+    /// During the pre-game phase, shows the setting currently being adjusted (or the settings
+    /// menu) instead. Once a player's flag has fallen, shows which player has run out of time in
+    /// the header, and otherwise the move number between players' names (space permitting).
+    /// The time of a player low on time flashes, along with their LED.
+    pub fn display_text(&self, now: u64) -> [String<32>; 2] {
+        if let (GameStatus::PreGame, Some(menu)) = (self.phase, &self.menu) {
+            return menu.display_text(|value| self.value_text(value));
+        }
+        let mut header: String<32> = String::new();
+        let mut buf: String<32> = String::new();
+        let time_control = self.red_player.time_control;
+        match (&self.phase, self.setting) {
+            (GameStatus::PreGame, Setting::Preset) => {
+                header.push_str("     Preset     ").unwrap();
+                core::write!(&mut buf, "{:^16}", self.preset_label()).unwrap();
+            }
+            (GameStatus::PreGame, Setting::Link) => {
+                header.push_str("    Players     ").unwrap();
+                core::write!(&mut buf, "{:^16}", self.link.label()).unwrap();
+            }
+            (GameStatus::PreGame, Setting::Time) => {
+                core::write!(&mut header, "Red{:^9}Blue", self.cursor.label()).unwrap();
+                core::write!(
+                    &mut buf,
+                    "{:<8}{:>8}",
+                    formatted_setting(self.red_player.millis_left),
+                    formatted_setting(self.blue_player.millis_left)
+                )
+                .unwrap();
+            }
+            (GameStatus::PreGame, Setting::Mode) => {
+                header.push_str("  Time control  ").unwrap();
+                core::write!(&mut buf, "{:^16}", time_control.label()).unwrap();
+            }
+            (GameStatus::PreGame, Setting::Stages) => {
+                header.push_str("     Stages     ").unwrap();
+                core::write!(&mut buf, "{:^16}", self.red_player.plan().label).unwrap();
+            }
+            (GameStatus::PreGame, Setting::Flag) => {
+                header.push_str("   Flag fall    ").unwrap();
+                core::write!(&mut buf, "{:^16}", self.flag_behavior.label()).unwrap();
+            }
+            (GameStatus::PreGame, Setting::Bonus) => {
+                core::write!(&mut header, "Red{:^9}Blue", self.cursor.label()).unwrap();
+                core::write!(
+                    &mut buf,
+                    "{:<8}{:>8}",
+                    self.red_player.formatted_bonus(),
+                    self.blue_player.formatted_bonus()
+                )
+                .unwrap();
+            }
+            (GameStatus::PreGame, Setting::Overtime) => {
+                let (label, red, blue) = match time_control {
+                    TimeControl::Canadian => (
+                        "Moves",
+                        self.red_player.overtime.block_moves,
+                        self.blue_player.overtime.block_moves,
+                    ),
+                    _ => (
+                        "Periods",
+                        self.red_player.overtime.periods,
+                        self.blue_player.overtime.periods,
+                    ),
+                };
+                core::write!(&mut header, "Red{:^9}Blue", label).unwrap();
+                core::write!(&mut buf, "{:<8}{:>8}", red, blue).unwrap();
+            }
+            _ => {
+                if let GameStatus::Flagged(loser) = self.phase {
+                    let mut flag: String<16> = String::new();
+                    core::write!(&mut flag, "{} flag", loser.name()).unwrap();
+                    core::write!(&mut header, "{:^16}", flag).unwrap();
+                } else {
+                    let mut red_label: String<16> = String::new();
+                    let mut blue_label: String<16> = String::new();
+                    core::write!(&mut red_label, "Red {}", self.red_player.status_tag(now))
+                        .unwrap();
+                    core::write!(&mut blue_label, "{} Blue", self.blue_player.status_tag(now))
+                        .unwrap();
+                    let (red_label, blue_label) = (red_label.trim_end(), blue_label.trim_start());
+                    let mut number: String<8> = String::new();
+                    core::write!(&mut number, "#{}", self.move_number()).unwrap();
+                    // centered, with at least a space on either side
+                    let left = (DISPLAY_COLUMNS - number.len()) / 2;
+                    let right = DISPLAY_COLUMNS - left - number.len();
+                    if red_label.len() < left && blue_label.len() < right {
+                        core::write!(
+                            &mut header,
+                            "{:<left$}{}{:>right$}",
+                            red_label,
+                            number,
+                            blue_label
+                        )
+                        .unwrap();
+                    } else {
+                        core::write!(&mut header, "{:<8}{:>8}", red_label, blue_label).unwrap();
+                    }
+                }
+                let (red_millis, blue_millis) = self.times_remaining(now);
+                let red_millis = self.flag_behavior.displayed_millis(red_millis);
+                let blue_millis = self.flag_behavior.displayed_millis(blue_millis);
+                let status = |player: &Player, millis, color| match self.low_on_time() {
+                    Some(low) if low == color && !self.blink.0 => String::new(),
+                    _ => player.formatted_status(millis, now, self.tenths_millis()),
+                };
+                core::write!(
+                    &mut buf,
+                    "{:<8}{:>8}",
+                    status(&self.red_player, red_millis, Color::Red),
+                    status(&self.blue_player, blue_millis, Color::Blue)
+                )
+                .unwrap();
+            }
+        }
+        [header, buf]
+    }
+
+    /// Shows the game's display text (see [`Game::display_text`]) on the given display.
+    /// During play, shows only the players' times instead if the display supports it
+    /// (see [`ClockDisplay::show_times`]) and there is nothing else (e.g. a stage) to show.
+    pub fn render<D: ClockDisplay + ?Sized>(
+        &self,
+        display: &mut D,
+        now: u64,
+    ) -> Result<(), D::Error> {
+        if let Some((red_millis, blue_millis)) = self.plain_times(now) {
+            if display.show_times(red_millis, blue_millis, self.tenths_millis())? {
+                return Ok(());
+            }
+        }
+        let [header, status] = self.display_text(now);
+        display.show(&header, &status)
+    }
+
+    /// Returns players' displayed times (red, blue) during play, if the display shows nothing
+    /// but the times, i.e. no stage tags, no byo-yomi periods or moves left in an overtime block
+    /// and no time flashing low.
+    fn plain_times(&self, now: u64) -> Option<(i32, i32)> {
+        if self.phase != GameStatus::Active || self.low_on_time().is_some() {
+            return None;
+        }
+        let (red_millis, blue_millis) = self.times_remaining(now);
+        let red_millis = self.flag_behavior.displayed_millis(red_millis);
+        let blue_millis = self.flag_behavior.displayed_millis(blue_millis);
+        for (player, millis) in [
+            (&self.red_player, red_millis),
+            (&self.blue_player, blue_millis),
+        ] {
+            if !player.status_tag(now).is_empty()
+                || player.formatted_status(millis, now, self.tenths_millis())
+                    != formatted_time(millis, self.tenths_millis())
+            {
+                return None;
+            }
+        }
+        Some((red_millis, blue_millis))
+    }
+
+    /// Returns the threshold below which times are shown in tenths of a second.
+    fn tenths_millis(&self) -> i32 {
+        self.tenths_secs as i32 * SECS_TO_MILLIS
+    }
+
+    /// Returns players' time remaining (red, blue), accounting for the turn in progress (if any).
+    /// In hourglass mode, time drained from the active player is shown on their opponent's clock.
+    pub fn times_remaining(&self, now: u64) -> (i32, i32) {
+        let mut red_millis = self.red_player.time_remaining(now);
+        let mut blue_millis = self.blue_player.time_remaining(now);
+        if self.red_player.time_control == TimeControl::Hourglass {
+            red_millis += self.blue_player.time_used(now);
+            blue_millis += self.red_player.time_used(now);
+        }
+        (red_millis, blue_millis)
+    }
+
+    /// Start the given player's clock (i.e. when their opponent resumes a paused game).
+    /// The opponent's paused turn (if any) is dropped.
+    fn start_turn(&mut self, color: Color, now: u64) {
+        match color {
+            Color::Red => {
+                self.blue_player.drop_turn();
+                self.red_player.start_turn(now)
+            }
+            Color::Blue => {
+                self.red_player.drop_turn();
+                self.blue_player.start_turn(now)
+            }
+            Color::Yellow => (),
+        }
+        self.phase = GameStatus::Active;
+    }
+
+    /// End the given player's turn (completing a move) and start their opponent's clock.
+    /// In hourglass mode, the time used is added to the opponent's clock.
+    fn end_turn(&mut self, color: Color, now: u64) {
+        match color {
+            Color::Red => {
+                let time_used = self.red_player.end_turn(now);
+                self.blue_player.credit_hourglass(time_used);
+                self.blue_player.start_turn(now);
+            }
+            Color::Blue => {
+                let time_used = self.blue_player.end_turn(now);
+                self.red_player.credit_hourglass(time_used);
+                self.red_player.start_turn(now);
+            }
+            Color::Yellow => (),
+        }
+    }
+
+    /// Stop both players' clocks without completing a move (i.e. when the game is paused).
+    fn pause_clocks(&mut self, now: u64) {
+        let red_time_used = self.red_player.pause_turn(now);
+        let blue_time_used = self.blue_player.pause_turn(now);
+        self.red_player.credit_hourglass(blue_time_used);
+        self.blue_player.credit_hourglass(red_time_used);
+    }
+
+    /// End the game if either player's time has run out, freezing both clocks at that point.
+    /// Has no effect if the clocks are set to count into negative time.
+    fn check_flag(&mut self, now: u64) {
+        if !self.flag_behavior.ends_game() {
+            return;
+        }
+        let (red_millis, blue_millis) = self.times_remaining(now);
+        let loser = if red_millis <= 0 {
+            Color::Red
+        } else if blue_millis <= 0 {
+            Color::Blue
+        } else {
+            return;
+        };
+        self.pause_clocks(now);
+        match loser {
+            Color::Red => self.red_player.millis_left = 0,
+            _ => self.blue_player.millis_left = 0,
+        }
+        self.phase = GameStatus::Flagged(loser);
+        self.blink = (false, now);
+    }
+
+    /// Returns true if either player's time has just dropped below the warning threshold,
+    /// i.e. it was not below it when last checked.
+    fn check_low_time(&mut self, now: u64) -> bool {
+        let threshold = self.warning_secs as i32 * SECS_TO_MILLIS;
+        let (red_millis, blue_millis) = self.times_remaining(now);
+        let low_time = [red_millis, blue_millis].map(|millis| threshold > 0 && millis < threshold);
+        let dropped = low_time
+            .iter()
+            .zip(self.low_time)
+            .any(|(is_low, was_low)| *is_low && !was_low);
+        self.low_time = low_time;
+        dropped
+    }
+
+    /// Adjust the given pre-game setting for the given player by the specified step.
+    /// The time control mode, stages and flag behavior are shared,
+    /// so adjusting them from either side changes both players.
+    /// Other settings are applied to the opponent as the players are linked (see [`Link`]).
+    fn adjust_setting(&mut self, setting: Setting, color: Color, step: i32) {
+        match (setting, color) {
+            (Setting::Preset, Color::Red) => self.cycle_preset(false),
+            (Setting::Preset, Color::Blue) => self.cycle_preset(true),
+            (Setting::Link, _) => {
+                self.link = self.link.next();
+                for setting in [Setting::Time, Setting::Bonus, Setting::Overtime] {
+                    self.follow(self.link, setting, Color::Red);
+                }
+            }
+            (Setting::Flag, _) => self.flag_behavior = self.flag_behavior.next(),
+            (Setting::Mode, _) => {
+                let time_control = self.red_player.time_control.next();
+                self.red_player.time_control = time_control;
+                self.blue_player.time_control = time_control;
+                self.red_player.bonus_millis = 0;
+                self.blue_player.bonus_millis = 0;
+            }
+            (Setting::Stages, _) => {
+                let stage_plan = (self.red_player.stage_plan + 1) % STAGE_PLANS.len();
+                self.red_player.set_stage_plan(stage_plan);
+                self.blue_player.set_stage_plan(stage_plan);
+                self.follow(self.link, Setting::Time, Color::Red);
+            }
+            (setting, Color::Red) => {
+                self.red_player.adjust(setting, self.cursor, step);
+                self.follow(self.link, setting, Color::Red);
+            }
+            (setting, Color::Blue) => {
+                self.blue_player.adjust(setting, self.cursor, step);
+                self.follow(self.link, setting, Color::Blue);
+            }
+            (_, Color::Yellow) => (),
+        }
+    }
+
+    /// Apply the given player's setting to their opponent, as the given link requires:
+    /// alike when linked, or their share of the time at odds.
+    fn follow(&mut self, link: Link, setting: Setting, color: Color) {
+        let (player, opponent) = match color {
+            Color::Blue => (&self.blue_player, &mut self.red_player),
+            _ => (&self.red_player, &mut self.blue_player),
+        };
+        match (link, setting) {
+            (Link::Linked, Setting::Time) => opponent.millis_left = player.millis_left,
+            (Link::Linked, Setting::Bonus) => opponent.bonus_millis = player.bonus_millis,
+            (Link::Linked, Setting::Overtime) => {
+                opponent.overtime.periods = player.overtime.periods;
+                opponent.overtime.block_moves = player.overtime.block_moves;
+            }
+            (Link::Odds(red, blue), Setting::Time) => {
+                let (parts, of_parts) = match color {
+                    Color::Blue => (red, blue),
+                    _ => (blue, red),
+                };
+                opponent.set_time_share(player.millis_left, parts, of_parts);
+            }
+            _ => (),
+        }
+    }
+
+    /// Move through the settings menu, applying any change to the selected value.
+    /// Returns true if a value was confirmed.
+    fn handle_menu(&mut self, button: ButtonEvent) -> bool {
+        let Some(menu) = &mut self.menu else {
+            return false;
+        };
+        match menu.handle(button) {
+            Outcome::Moved => (),
+            Outcome::Adjust(value, field, step) => self.adjust_value(value, field, step),
+            Outcome::Confirmed => return true,
+            Outcome::Closed => self.menu = None,
+        }
+        false
+    }
+
+    /// Adjust the given value of the settings menu by the specified step (of the given field,
+    /// for times). Times and bonuses are adjusted from Red's and set for both players
+    /// (Blue's time being set to their share of it if the players' times are at odds).
+    fn adjust_value(&mut self, value: Value, field: Field, step: i32) {
+        match value {
+            Value::Setting(setting @ (Setting::Time | Setting::Bonus)) => {
+                self.red_player.adjust(setting, field, step);
+                let link = match (self.link, setting) {
+                    (Link::Odds(..), Setting::Time) => self.link,
+                    _ => Link::Linked,
+                };
+                self.follow(link, setting, Color::Red);
+            }
+            Value::Setting(setting) => self.adjust_setting(setting, Color::Red, step),
+            Value::Sound => self.sound = !self.sound,
+            Value::Backlight => self.backlight = !self.backlight,
+            Value::Warning => {
+                let index = WARNING_SECS
+                    .iter()
+                    .position(|secs| *secs == self.warning_secs)
+                    .unwrap_or(0);
+                self.warning_secs = WARNING_SECS[(index + 1) % WARNING_SECS.len()];
+            }
+            Value::Tenths => {
+                let index = TENTHS_SECS
+                    .iter()
+                    .position(|secs| *secs == self.tenths_secs)
+                    .unwrap_or(0);
+                self.tenths_secs = TENTHS_SECS[(index + 1) % TENTHS_SECS.len()];
+            }
+        }
+    }
+
+    /// Returns the given value of the settings menu as shown on the LCD
+    /// (e.g. "0:10:00", or "Per player" if the players' values differ).
+    fn value_text(&self, value: Value) -> String<16> {
+        let shared = |text: &str| (String::from(text), String::from(text));
+        let on_off = |on| shared(if on { "On" } else { "Off" });
+        let (red, blue) = match value {
+            Value::Setting(Setting::Time) => (
+                formatted_setting(self.red_player.millis_left),
+                formatted_setting(self.blue_player.millis_left),
+            ),
+            Value::Setting(Setting::Bonus) => (
+                self.red_player.formatted_bonus(),
+                self.blue_player.formatted_bonus(),
+            ),
+            Value::Setting(Setting::Flag) => shared(self.flag_behavior.label()),
+            Value::Setting(Setting::Link) => shared(&self.link.label()),
+            Value::Setting(_) => shared(self.red_player.time_control.label()),
+            Value::Sound => on_off(self.sound),
+            Value::Backlight => on_off(self.backlight),
+            Value::Warning if self.warning_secs == 0 => shared("Off"),
+            Value::Warning | Value::Tenths => {
+                let secs = match value {
+                    Value::Warning => self.warning_secs,
+                    _ => self.tenths_secs,
+                };
+                let mut text: String<16> = String::new();
+                core::write!(&mut text, "{} s", secs).unwrap();
+                shared(&text)
+            }
+        };
+        let mut buf: String<16> = String::new();
+        buf.push_str(if red == blue { &red } else { "Per player" })
+            .unwrap();
+        buf
+    }
+
+    /// Advance to the next field of the current time, or else to the next pre-game setting,
+    /// starting the game (paused) after the last one, or at once if a preset is selected.
+    fn next_setting(&mut self) {
+        if let Some(field) = self.cursor.next_in(Field::of(self.setting)) {
+            self.cursor = field;
+            return;
+        }
+        match self.setting {
+            Setting::Preset if self.current_preset().is_some() => self.phase = GameStatus::Paused,
+            Setting::Preset => self.setting = Setting::Link,
+            Setting::Link => self.setting = Setting::Time,
+            Setting::Time => self.setting = Setting::Mode,
+            Setting::Mode => self.setting = Setting::Bonus,
+            Setting::Bonus if self.red_player.time_control.has_overtime() => {
+                self.setting = Setting::Overtime
+            }
+            Setting::Bonus | Setting::Overtime => self.setting = Setting::Stages,
+            Setting::Stages => self.setting = Setting::Flag,
+            Setting::Flag => {
+                self.setting = Setting::Preset;
+                self.phase = GameStatus::Paused;
+            }
+        }
+        self.cursor = Field::first(self.setting);
+    }
+
+    /// Returns the index of the preset matching the current time control, counting the built-in
+    /// presets followed by the custom ones.
+    fn current_preset(&self) -> Option<usize> {
+        let time = self.time_settings();
+        PRESETS
+            .iter()
+            .map(|preset| &preset.time)
+            .chain(&self.custom_presets)
+            .position(|preset| *preset == time)
+    }
+
+    /// Returns the name of the preset matching the current time control
+    /// ("Custom N" for custom presets, "Current" if none match).
+    fn preset_label(&self) -> String<16> {
+        let mut label: String<16> = String::new();
+        match self.current_preset() {
+            Some(i) if i < PRESETS.len() => label.push_str(PRESETS[i].name).unwrap(),
+            Some(i) => core::write!(&mut label, "Custom {}", i - PRESETS.len() + 1).unwrap(),
+            None => label.push_str("Current").unwrap(),
+        }
+        label
+    }
+
+    /// Select the next (or previous) preset, starting from the first (or last) one
+    /// if the current time control doesn't match any.
+    fn cycle_preset(&mut self, forward: bool) {
+        let count = PRESETS.len() + self.custom_presets.len();
+        let index = match (self.current_preset(), forward) {
+            (Some(i), true) => (i + 1) % count,
+            (Some(i), false) => (i + count - 1) % count,
+            (None, true) => 0,
+            (None, false) => count - 1,
+        };
+        let time = match PRESETS.get(index) {
+            Some(preset) => preset.time,
+            None => self.custom_presets[index - PRESETS.len()],
+        };
+        self.apply_time_settings(time);
+    }
+
+    /// Save the current time control as a custom preset (replacing the oldest one if full),
+    /// or delete it if it is already a custom preset.
+    fn save_or_delete_preset(&mut self) {
+        match self.current_preset() {
+            Some(i) if i < PRESETS.len() => (),
+            Some(i) => {
+                self.custom_presets.remove(i - PRESETS.len());
+            }
+            None => {
+                if self.custom_presets.is_full() {
+                    self.custom_presets.remove(0);
+                }
+                let _ = self.custom_presets.push(self.time_settings());
+            }
+        }
+    }
+
+    fn apply_time_settings(&mut self, time: TimeSettings) {
+        for (player, settings) in [&mut self.red_player, &mut self.blue_player]
+            .into_iter()
+            .zip(time.players)
+        {
+            player.time_control = time.time_control;
+            player.stage_plan = time.stage_plan as usize;
+            player.apply(settings);
+        }
+    }
+
+    /// Reset all state to initiate a new game, with the settings of the last game started.
+    fn reset(&mut self) {
+        self.phase = GameStatus::PreGame;
+        self.setting = Setting::Preset;
+        self.cursor = Field::Hours;
+        self.menu = None;
+        self.flag_behavior = self.saved.flag_behavior;
+        self.link = self.saved.link;
+        self.sound = self.saved.sound;
+        self.backlight = self.saved.backlight;
+        self.warning_secs = self.saved.warning_secs;
+        self.tenths_secs = self.saved.tenths_secs;
+        self.low_time = [false; 2];
+        self.red_player.reset();
+        self.blue_player.reset();
+        self.apply_time_settings(self.saved.time);
+    }
+}
+
+/// Player setting adjusted by the Red/Blue buttons during the pre-game phase.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Setting {
+    /// Named time control (see [`crate::presets`]): Red/Blue select the previous/next preset;
+    /// holding either saves the current time control as a custom preset (or deletes it).
+    /// Yellow starts the game with the selected preset (which can be fine-tuned in the settings
+    /// menu), or moves on to set up the current time control page by page.
+    Preset,
+    /// How players' settings are linked (see [`crate::time_control::Link`]).
+    Link,
+    Time,
+    Mode,
+    Bonus,
+    Overtime,
+    Stages,
+    Flag,
+}
+
+/// Field of a time setting (e.g. the minutes of a player's time) adjusted by the Red/Blue buttons
+/// during the pre-game phase, selected in turn with Yellow.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Field {
+    Hours,
+    Minutes,
+    Seconds,
+}
+
+impl Field {
+    /// Returns the fields of the given setting, in the order they are edited:
+    /// hours, minutes and seconds of times; minutes and seconds of bonuses; none otherwise.
+    pub fn of(setting: Setting) -> &'static [Field] {
+        match setting {
+            Setting::Time => &[Field::Hours, Field::Minutes, Field::Seconds],
+            Setting::Bonus => &[Field::Minutes, Field::Seconds],
+            _ => &[],
+        }
+    }
+
+    /// Returns the first field of the given setting (hours if it has none).
+    pub fn first(setting: Setting) -> Field {
+        Field::of(setting).first().copied().unwrap_or(Field::Hours)
+    }
+
+    /// Returns the field after this one in the given fields, or None if this is the last one.
+    pub fn next_in(self, fields: &[Field]) -> Option<Field> {
+        let index = fields.iter().position(|field| *field == self)?;
+        fields.get(index + 1).copied()
+    }
+
+    /// Name of the field, as shown on the LCD (max 9 chars).
+    pub fn label(self) -> &'static str {
+        match self {
+            Field::Hours => "Hours",
+            Field::Minutes => "Minutes",
+            Field::Seconds => "Seconds",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameStatus {
+    PreGame,
+    Active,
+    Paused,
+    /// Game over: the given player has run out of time.
+    Flagged(Color),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FrameBuffer;
+
+    const PRESS_RED: Event = Event::Button(ButtonEvent::Pressed(Color::Red));
+    const PRESS_YELLOW: Event = Event::Button(ButtonEvent::Pressed(Color::Yellow));
+    const PRESS_BLUE: Event = Event::Button(ButtonEvent::Pressed(Color::Blue));
+    const HOLD_RED: Event = Event::Button(ButtonEvent::Held(Color::Red));
+    const HOLD_YELLOW: Event = Event::Button(ButtonEvent::Held(Color::Yellow));
+    const HOLD_BLUE: Event = Event::Button(ButtonEvent::Held(Color::Blue));
+    const EVENTS: [Event; 7] = [
+        PRESS_RED,
+        PRESS_YELLOW,
+        PRESS_BLUE,
+        HOLD_RED,
+        HOLD_YELLOW,
+        HOLD_BLUE,
+        Event::Tick,
+    ];
+
+    /// Returns a game which has been set up with the given time control and started (paused).
+    fn game(time_control: TimeControl) -> Game {
+        let mut game = Game::new();
+        while game.red_player.time_control != time_control {
+            game.setting = Setting::Mode;
+            game.adjust_setting(Setting::Mode, Color::Red, 1);
+        }
+        game.setting = Setting::Time;
+        game.phase = GameStatus::Paused;
+        game
+    }
+
+    /// Returns a game in the given phase (Blue's clock running if active, Red flagged if over).
+    fn game_in(phase: GameStatus) -> Game {
+        let mut game = game(TimeControl::Increment);
+        match phase {
+            GameStatus::PreGame => game.reset(),
+            GameStatus::Paused => (),
+            GameStatus::Active => {
+                game.handle(PRESS_RED, 0);
+            }
+            GameStatus::Flagged(_) => {
+                game.handle(PRESS_BLUE, 0);
+                game.handle(Event::Tick, 700_000);
+            }
+        }
+        assert_eq!(game.phase(), phase);
+        game
+    }
+
+    #[test]
+    fn every_event_in_every_phase() {
+        use GameStatus::*;
+        let flagged = Flagged(Color::Red);
+        let expected = [
+            (
+                PreGame,
+                [
+                    PreGame, PreGame, PreGame, PreGame, PreGame, PreGame, PreGame,
+                ],
+            ),
+            (
+                Paused,
+                [Active, Paused, Active, Paused, PreGame, Paused, Paused],
+            ),
+            (
+                Active,
+                [Active, Paused, Active, Active, PreGame, Active, Active],
+            ),
+            (
+                flagged,
+                [
+                    flagged, flagged, flagged, flagged, PreGame, flagged, flagged,
+                ],
+            ),
+        ];
+        for (phase, next_phases) in expected {
+            for (event, next_phase) in EVENTS.into_iter().zip(next_phases) {
+                let mut game = game_in(phase);
+                game.handle(event, 1_000);
+                assert_eq!(game.phase(), next_phase, "{:?} in {:?}", event, phase);
+            }
+        }
+    }
+
+    #[test]
+    fn button_events_redraw_display() {
+        for phase in [GameStatus::PreGame, GameStatus::Paused, GameStatus::Active] {
+            for event in &EVENTS[..6] {
+                let mut game = game_in(phase);
+                let actions = game.handle(*event, 1_000);
+                assert!(actions.contains(&Action::Display));
+            }
+        }
+    }
+
+    #[test]
+    fn ticks_only_redraw_running_clocks() {
+        assert!(game_in(GameStatus::PreGame)
+            .handle(Event::Tick, 100)
+            .is_empty());
+        assert!(game_in(GameStatus::Paused)
+            .handle(Event::Tick, 100)
+            .is_empty());
+        assert_eq!(
+            game_in(GameStatus::Active).handle(Event::Tick, 100),
+            [Action::Display]
+        );
+    }
+
+    #[test]
+    fn pre_game_settings_cycle_and_start_paused() {
+        let mut game = Game::new();
+        let mut pages = 0;
+        while game.phase() == GameStatus::PreGame {
+            game.handle(PRESS_YELLOW, 0);
+            pages += 1;
+        }
+        assert_eq!(pages, 10);
+        assert_eq!(game.phase(), GameStatus::Paused);
+        assert_eq!(game.setting, Setting::Preset);
+
+        // a preset needs no further setup
+        let mut game = Game::new();
+        game.handle(PRESS_BLUE, 0);
+        game.handle(PRESS_YELLOW, 0);
+        assert_eq!(game.phase(), GameStatus::Paused);
+        assert_eq!(game.saved_settings().time, PRESETS[0].time);
+    }
+
+    #[test]
+    fn starting_with_new_settings_saves_them() {
+        let mut game = Game::new();
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(PRESS_RED, 0);
+        let mut actions = Actions::new();
+        while game.phase() == GameStatus::PreGame {
+            actions = game.handle(PRESS_YELLOW, 0);
+        }
+        assert_eq!(
+            actions,
+            [
+                Action::Led(Color::Yellow, true),
+                Action::Display,
+                Action::SaveSettings
+            ]
+        );
+        assert_eq!(
+            game.saved_settings().time.players[0].millis,
+            9 * 60_000 + 999
+        );
+
+        // pausing mid-game doesn't save, and resetting restores the settings last started with
+        game.handle(PRESS_BLUE, 0);
+        assert!(!game
+            .handle(PRESS_YELLOW, 5_000)
+            .contains(&Action::SaveSettings));
+        game.handle(HOLD_YELLOW, 5_000);
+        assert_eq!(game.red_player.millis_left, 9 * 60_000 + 999);
+        let same = (0..10).flat_map(|_| game.handle(PRESS_YELLOW, 6_000));
+        assert!(!same
+            .into_iter()
+            .any(|action| action == Action::SaveSettings));
+    }
+
+    #[test]
+    fn restored_settings_apply_to_both_players() {
+        let mut settings = Settings {
+            flag_behavior: FlagBehavior::CountNegative,
+            ..Settings::default()
+        };
+        settings.time.time_control = TimeControl::ByoYomi;
+        settings.time.players[1].bonus_millis = 30_000;
+        settings.time.players[1].periods = 3;
+        settings.custom_presets.push(PRESETS[2].time).unwrap();
+        let game = Game::with_settings(settings.clone());
+        assert_eq!(game.settings(), settings);
+        assert_eq!(game.phase(), GameStatus::PreGame);
+        assert_eq!(game.blue_player.time_control, TimeControl::ByoYomi);
+        assert_eq!(game.blue_player.overtime.periods, 3);
+    }
+
+    #[test]
+    fn presets_cycle_in_both_directions() {
+        let mut game = Game::new();
+        assert_eq!(game.display_text(0)[1], "    Current     ");
+        game.handle(PRESS_BLUE, 0);
+        assert_eq!(
+            game.display_text(0),
+            ["     Preset     ", "   Bullet 1+0   "]
+        );
+        game.handle(PRESS_BLUE, 0);
+        assert_eq!(game.display_text(0)[1], "   Blitz 3+2    ");
+        assert_eq!(game.blue_player.millis_left, 3 * 60_000 + 999);
+        assert_eq!(game.blue_player.bonus_millis, 2_000);
+        game.handle(PRESS_RED, 0);
+        game.handle(PRESS_RED, 0);
+        assert_eq!(game.display_text(0)[1], " FIDE 90/40+30  ");
+        assert_eq!(game.red_player.plan().label, "40/90 SD/30");
+
+        // fine-tuning a preset's settings no longer matches it
+        game.adjust_setting(Setting::Time, Color::Blue, 1);
+        assert_eq!(game.display_text(0)[1], "    Current     ");
+    }
+
+    #[test]
+    fn holding_saves_and_deletes_custom_presets() {
+        let mut game = Game::new();
+        let actions = game.handle(HOLD_RED, 0);
+        assert_eq!(actions, [Action::Display, Action::SaveSettings]);
+        assert_eq!(game.display_text(0)[1], "    Custom 1    ");
+        assert_eq!(game.saved_settings().custom_presets.len(), 1);
+        // holding on doesn't delete it again
+        let actions = game.handle(Event::Button(ButtonEvent::Repeated(Color::Red)), 0);
+        assert_eq!(actions, [Action::Display]);
+        assert_eq!(game.display_text(0)[1], "    Custom 1    ");
+        // built-in presets can't be deleted
+        game.handle(PRESS_BLUE, 0);
+        assert_eq!(game.handle(HOLD_BLUE, 0), [Action::Display]);
+        game.handle(PRESS_RED, 0);
+        assert_eq!(game.display_text(0)[1], "    Custom 1    ");
+        game.handle(HOLD_BLUE, 0);
+        assert_eq!(game.display_text(0)[1], "    Current     ");
+        assert!(game.saved_settings().custom_presets.is_empty());
+
+        // the oldest custom preset is replaced once full
+        for minutes in 1..=MAX_CUSTOM_PRESETS + 1 {
+            game.red_player.millis_left = minutes as i32 * 60_000;
+            game.handle(HOLD_RED, 0);
+        }
+        assert_eq!(game.custom_presets.len(), MAX_CUSTOM_PRESETS);
+        assert_eq!(game.custom_presets[0].players[0].millis, 2 * 60_000);
+    }
+
+    #[test]
+    fn settings_menu_adjusts_both_players() {
+        let mut game = Game::new();
+        let mut lcd = FrameBuffer::new();
+        game.handle(HOLD_YELLOW, 0);
+        game.render(&mut lcd, 0).unwrap();
+        assert_eq!(
+            [lcd.row(0), lcd.row(1)],
+            ["  Time control  ", "      ...       "]
+        );
+        for event in [
+            PRESS_YELLOW,
+            PRESS_BLUE,
+            PRESS_YELLOW,
+            PRESS_YELLOW,
+            PRESS_RED,
+        ] {
+            assert_eq!(game.handle(event, 0), [Action::Display]);
+        }
+        game.render(&mut lcd, 0).unwrap();
+        assert_eq!(
+            [lcd.row(0), lcd.row(1)],
+            [" Time: Minutes  ", "   [0:09:00]    "]
+        );
+        assert_eq!(game.blue_player.millis_left, 9 * 60_000 + 999);
+
+        // differing values are set from Red's
+        game.blue_player.millis_left = 5 * 60_000 + 999;
+        game.render(&mut lcd, 0).unwrap();
+        assert_eq!(lcd.row(1), "  [Per player]  ");
+        game.handle(PRESS_BLUE, 0);
+        assert_eq!(game.blue_player.millis_left, 8 * 60_000 + 999);
+        game.handle(PRESS_YELLOW, 0);
+        assert_eq!(
+            game.handle(PRESS_YELLOW, 0),
+            [Action::Display, Action::Sound(Sound::Confirm)]
+        );
+    }
+
+    #[test]
+    fn sound_and_backlight_are_saved_at_once() {
+        let mut game = Game::new();
+        game.handle(HOLD_YELLOW, 0);
+        for event in [PRESS_BLUE, PRESS_YELLOW] {
+            game.handle(event, 0);
+        }
+        let actions = game.handle(PRESS_RED, 0);
+        assert_eq!(actions, [Action::Display, Action::SaveSettings]);
+        assert!(!game.saved_settings().sound);
+        for event in [PRESS_YELLOW, PRESS_BLUE, PRESS_YELLOW] {
+            game.handle(event, 0);
+        }
+        let actions = game.handle(HOLD_BLUE, 0);
+        assert_eq!(
+            actions,
+            [
+                Action::Display,
+                Action::Backlight(false),
+                Action::SaveSettings
+            ]
+        );
+        assert!(!game.saved_settings().backlight);
+        // the menu closes from its "Exit" entry, back to the pre-game settings
+        for event in [
+            PRESS_YELLOW,
+            PRESS_BLUE,
+            PRESS_BLUE,
+            PRESS_BLUE,
+            PRESS_YELLOW,
+        ] {
+            game.handle(event, 0);
+        }
+        assert_eq!(game.display_text(0)[0], "     Preset     ");
+    }
+
+    #[test]
+    fn pre_game_hold_adjusts_by_five() {
+        let mut game = Game::new();
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(HOLD_BLUE, 0);
+        assert_eq!(game.red_player.millis_left, 10 * 60_000 + 999);
+        assert_eq!(game.blue_player.millis_left, 5 * 60_000 + 999);
+        game.handle(HOLD_YELLOW, 0);
+        assert_eq!(game.setting, Setting::Time);
+    }
+
+    #[test]
+    fn yellow_moves_through_the_fields_of_times() {
+        let mut game = Game::new();
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(PRESS_YELLOW, 0);
+        assert_eq!(
+            game.display_text(0),
+            ["Red  Hours  Blue", "0:10:00  0:10:00"]
+        );
+        game.handle(PRESS_BLUE, 0);
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(PRESS_RED, 0);
+        assert_eq!(
+            game.display_text(0),
+            ["Red Seconds Blue", "0:10:59  9:10:00"]
+        );
+        game.handle(PRESS_YELLOW, 0);
+        assert_eq!(game.setting, Setting::Mode);
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(HOLD_RED, 0);
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(PRESS_BLUE, 0);
+        assert_eq!(
+            game.display_text(0),
+            ["Red Seconds Blue", "5:00        0:01"]
+        );
+    }
+
+    #[test]
+    fn shared_settings_change_both_players() {
+        let mut game = Game::new();
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(PRESS_RED, 0);
+        assert_eq!(game.red_player.millis_left, 9 * 60_000 + 999);
+        assert_eq!(game.blue_player.millis_left, 10 * 60_000 + 999);
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(PRESS_BLUE, 0);
+        assert!(game.red_player.time_control == TimeControl::Delay);
+        assert!(game.blue_player.time_control == TimeControl::Delay);
+    }
+
+    #[test]
+    fn linked_players_are_adjusted_alike() {
+        let mut game = Game::new();
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(PRESS_BLUE, 0);
+        assert_eq!(
+            game.display_text(0),
+            ["    Players     ", "     Linked     "]
+        );
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(PRESS_BLUE, 0);
+        assert_eq!(game.display_text(0)[1], "0:09:00  0:09:00");
+        game.setting = Setting::Bonus;
+        game.cursor = Field::Seconds;
+        game.handle(HOLD_RED, 0);
+        assert_eq!(game.blue_player.bonus_millis, 5_000);
+        // selecting a link applies it at once
+        game.blue_player.overtime.periods = 3;
+        game.link = Link::Unlinked;
+        game.adjust_setting(Setting::Link, Color::Red, 1);
+        assert_eq!(game.blue_player.overtime.periods, 5);
+    }
+
+    #[test]
+    fn times_at_odds_keep_their_ratio() {
+        let mut game = Game::new();
+        game.handle(PRESS_YELLOW, 0);
+        for _ in 0..4 {
+            game.handle(PRESS_RED, 0);
+        }
+        assert_eq!(game.display_text(0)[1], "    Odds 5:1    ");
+        assert_eq!(game.blue_player.millis_left, 2 * 60_000 + 999);
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(PRESS_BLUE, 0);
+        assert_eq!(game.display_text(0)[1], "0:05:00  0:01:00");
+        // bonuses are adjusted separately
+        game.setting = Setting::Bonus;
+        game.cursor = Field::Seconds;
+        game.handle(PRESS_BLUE, 0);
+        assert_eq!(game.red_player.bonus_millis, 0);
+        assert_eq!(game.blue_player.bonus_millis, 1_000);
+        // starting saves the link, which is restored on reset
+        game.setting = Setting::Flag;
+        game.handle(PRESS_YELLOW, 0);
+        game.handle(HOLD_YELLOW, 0);
+        assert_eq!(game.saved_settings().link, Link::Odds(5, 1));
+        assert_eq!(game.link, Link::Odds(5, 1));
+        assert_eq!(game.blue_player.millis_left, 60_000 + 999);
+    }
+
+    #[test]
+    fn stages_keep_the_selected_times_and_odds() {
+        let mut game = Game::new();
+        game.link = Link::Odds(5, 1);
+        game.red_player.millis_left = 5 * 60_000 + 999;
+        game.blue_player.millis_left = 60_000 + 999;
+        game.adjust_setting(Setting::Stages, Color::Red, 1);
+        assert_eq!(game.red_player.plan().label, "40/90 SD/30");
+        assert_eq!(game.red_player.millis_left, 90 * 60_000 + 999);
+        assert_eq!(game.blue_player.millis_left, 18 * 60_000 + 999);
+        // cycling back to a single stage leaves the times as they were
+        while game.red_player.stage_plan != 0 {
+            game.adjust_setting(Setting::Stages, Color::Red, 1);
+        }
+        assert_eq!(game.red_player.millis_left, 120 * 60_000 + 999);
+        assert_eq!(game.blue_player.millis_left, 24 * 60_000 + 999);
+    }
+
+    #[test]
+    fn resuming_lights_opponent_led() {
+        let mut game = game(TimeControl::Increment);
+        let actions = game.handle(PRESS_RED, 0);
+        assert_eq!(
+            actions,
+            [
+                Action::Led(Color::Yellow, false),
+                Action::Led(Color::Blue, true),
+                Action::Display,
+                Action::Sound(Sound::Click)
+            ]
+        );
+    }
+
+    #[test]
+    fn end_turn_starts_opponent() {
+        let mut game = game(TimeControl::Increment);
+        game.handle(PRESS_BLUE, 0);
+        let actions = game.handle(PRESS_RED, 2_000);
+        assert_eq!(
+            actions,
+            [
+                Action::Led(Color::Red, false),
+                Action::Led(Color::Blue, true),
+                Action::Display,
+                Action::Sound(Sound::Click)
+            ]
+        );
+        assert_eq!(game.times_remaining(5_000), (598_999, 597_999));
+    }
+
+    #[test]
+    fn pause_and_reset_switch_yellow_led() {
+        let mut game = game_in(GameStatus::Active);
+        let actions = game.handle(PRESS_YELLOW, 1_000);
+        assert_eq!(
+            actions,
+            [
+                Action::Led(Color::Yellow, true),
+                Action::Led(Color::Blue, false),
+                Action::Display
+            ]
+        );
+        assert_eq!(game.blue_player.millis_left, 599_999);
+        let actions = game.handle(HOLD_YELLOW, 2_000);
+        assert_eq!(
+            actions,
+            [Action::Led(Color::Yellow, false), Action::Display]
+        );
+    }
+
+    #[test]
+    fn low_time_warns_then_flashes_led_and_time() {
+        let mut game = game(TimeControl::Increment);
+        game.handle(PRESS_RED, 0);
+        assert_eq!(game.handle(Event::Tick, 570_000), [Action::Display]);
+        let actions = game.handle(Event::Tick, 571_000);
+        assert_eq!(actions, [Action::Display, Action::Sound(Sound::Warning)]);
+        let actions = game.handle(Event::Tick, 571_500);
+        assert_eq!(actions, [Action::Led(Color::Blue, false), Action::Display]);
+        assert_eq!(game.display_text(571_500)[1], "10:00           ");
+        assert_eq!(game.plain_times(571_500), None);
+        assert_eq!(game.handle(Event::Tick, 571_600), [Action::Display]);
+        let actions = game.handle(Event::Tick, 572_000);
+        assert_eq!(actions, [Action::Led(Color::Blue, true), Action::Display]);
+        assert_eq!(game.display_text(572_000)[1], "10:00      00:28");
+
+        // only the active player's LED and time flash
+        game.handle(Event::Tick, 572_500);
+        let actions = game.handle(PRESS_BLUE, 572_600);
+        assert_eq!(
+            actions,
+            [
+                Action::Led(Color::Red, true),
+                Action::Display,
+                Action::Sound(Sound::Click)
+            ]
+        );
+        assert_eq!(game.display_text(573_100)[1], "10:00      00:28");
+        assert!(game.plain_times(573_100).is_some());
+    }
+
+    #[test]
+    fn busiest_events_fit_in_actions() {
+        let mut game = game(TimeControl::Increment);
+        game.warning_secs = 10;
+        game.blue_player.millis_left = 5_999;
+        game.handle(PRESS_BLUE, 0);
+        // Red's move takes them below the threshold as Blue, already low, takes over
+        let actions = game.handle(PRESS_RED, 591_500);
+        assert_eq!(
+            actions,
+            [
+                Action::Led(Color::Red, false),
+                Action::Led(Color::Blue, true),
+                Action::Display,
+                Action::Sound(Sound::Warning)
+            ]
+        );
+        // Blue's flag falls as they move, with their LED lit
+        let actions = game.handle(PRESS_BLUE, 598_000);
+        assert_eq!(game.phase(), GameStatus::Flagged(Color::Blue));
+        assert_eq!(
+            actions,
+            [
+                Action::Led(Color::Blue, false),
+                Action::Display,
+                Action::Sound(Sound::Flag)
+            ]
+        );
+        assert!(actions.len() < actions.capacity());
+    }
+
+    #[test]
+    fn sounds_are_only_played_with_sound_on() {
+        let mut game = game(TimeControl::Increment);
+        game.warning_secs = 10;
+        // a time already below the threshold once the game has started doesn't warn
+        game.red_player.millis_left = 5_000;
+        game.handle(Event::Tick, 0);
+        let actions = game.handle(PRESS_BLUE, 0);
+        assert_eq!(actions.last(), Some(&Action::Sound(Sound::Click)));
+        game.sound = false;
+        let actions = game.handle(PRESS_RED, 1_000);
+        assert!(!actions
+            .iter()
+            .any(|action| matches!(action, Action::Sound(_))));
+        // the threshold is selected in the settings menu
+        game.handle(HOLD_YELLOW, 2_000);
+        game.handle(HOLD_YELLOW, 2_000);
+        for event in [PRESS_RED, PRESS_RED, PRESS_RED, PRESS_YELLOW, PRESS_BLUE] {
+            game.handle(event, 2_000);
+        }
+        assert_eq!(game.display_text(2_000)[1], "     [60 s]     ");
+        assert_eq!(game.saved_settings().warning_secs, 60);
+    }
+
+    #[test]
+    fn tenths_are_shown_below_the_selected_threshold() {
+        let mut game = game(TimeControl::Increment);
+        game.warning_secs = 0;
+        game.red_player.millis_left = 15_000;
+        game.blue_player.millis_left = 25_000;
+        assert_eq!(game.display_text(0)[1], "00:15      00:25");
+        game.tenths_secs = 20;
+        assert_eq!(game.display_text(0)[1], "15.0       00:25");
+        game.blue_player.millis_left = 19_999;
+        assert_eq!(game.display_text(0)[1], "15.0        19.9");
+        // the threshold is selected in the settings menu
+        let mut game = Game::new();
+        game.handle(HOLD_YELLOW, 0);
+        for event in [PRESS_RED, PRESS_RED, PRESS_YELLOW, PRESS_BLUE] {
+            game.handle(event, 0);
+        }
+        assert_eq!(
+            game.display_text(0),
+            ["  Tenths below  ", "     [20 s]     "]
+        );
+        assert_eq!(game.saved_settings().tenths_secs, 20);
+        game.handle(PRESS_BLUE, 0);
+        assert_eq!(game.saved_settings().tenths_secs, 10);
+    }
+
+    #[test]
+    fn hourglass_transfers_time_to_opponent() {
+        let mut game = game(TimeControl::Hourglass);
+        game.handle(PRESS_BLUE, 0);
+        assert_eq!(game.times_remaining(3_000), (597_999, 603_999));
+        game.handle(PRESS_RED, 3_000);
+        game.handle(PRESS_YELLOW, 4_000);
+        assert_eq!(game.red_player.millis_left, 598_999);
+        assert_eq!(game.blue_player.millis_left, 602_999);
+    }
+
+    #[test]
+    fn flag_fall_ends_game_and_blinks_led() {
+        let mut game = game(TimeControl::Increment);
+        game.warning_secs = 0;
+        game.handle(PRESS_RED, 0);
+        game.handle(Event::Tick, 600_000);
+        assert_eq!(game.phase(), GameStatus::Active);
+        let actions = game.handle(Event::Tick, 601_000);
+        assert_eq!(
+            actions,
+            [
+                Action::Led(Color::Blue, false),
+                Action::Display,
+                Action::Sound(Sound::Flag)
+            ]
+        );
+        assert_eq!(game.phase(), GameStatus::Flagged(Color::Blue));
+        assert_eq!(game.blue_player.millis_left, 0);
+        let [header, row] = game.display_text(601_000);
+        assert_eq!(header, "   Blue flag    ");
+        assert_eq!(row, "10:00        0.0");
+
+        assert!(game.handle(Event::Tick, 601_100).is_empty());
+        let actions = game.handle(Event::Tick, 601_500);
+        assert_eq!(actions, [Action::Led(Color::Blue, true), Action::Display]);
+        let actions = game.handle(Event::Tick, 602_000);
+        assert_eq!(actions, [Action::Led(Color::Blue, false), Action::Display]);
+        game.handle(Event::Tick, 602_500);
+        let actions = game.handle(HOLD_YELLOW, 602_600);
+        assert_eq!(actions, [Action::Led(Color::Blue, false), Action::Display]);
+    }
+
+    #[test]
+    fn flag_falls_before_a_late_press_counts() {
+        for late_press in [PRESS_BLUE, PRESS_YELLOW] {
+            let mut game = game(TimeControl::Increment);
+            game.warning_secs = 0;
+            game.red_player.bonus_millis = 2_000;
+            game.blue_player.bonus_millis = 2_000;
+            game.handle(PRESS_RED, 0);
+            game.handle(Event::Tick, 600_950);
+            assert_eq!(game.times_remaining(600_950).1, 49);
+            let actions = game.handle(late_press, 601_050);
+            assert_eq!(
+                actions,
+                [
+                    Action::Led(Color::Blue, false),
+                    Action::Display,
+                    Action::Sound(Sound::Flag)
+                ],
+                "{late_press:?}"
+            );
+            assert_eq!(game.phase(), GameStatus::Flagged(Color::Blue));
+            assert_eq!(game.times_remaining(601_050), (600_999, 0));
+            assert_eq!(game.moves(), (0, 0));
+        }
+    }
+
+    #[test]
+    fn counting_negative_never_flags() {
+        let mut game = game(TimeControl::Increment);
+        game.flag_behavior = game.flag_behavior.next();
+        game.warning_secs = 0;
+        game.handle(PRESS_BLUE, 0);
+        game.handle(Event::Tick, 662_000);
+        assert_eq!(game.phase(), GameStatus::Active);
+        let [header, row] = game.display_text(662_000);
+        assert_eq!(header, "Red    #1   Blue");
+        assert_eq!(row, "-01:01     10:00");
+    }
+
+    #[test]
+    fn move_number_counts_both_players_moves() {
+        let mut game = game_in(GameStatus::Active);
+        assert_eq!((game.moves(), game.move_number()), ((0, 0), 1));
+        game.handle(PRESS_BLUE, 1_000);
+        assert_eq!((game.moves(), game.move_number()), ((0, 1), 1));
+        game.handle(PRESS_RED, 2_000);
+        assert_eq!((game.moves(), game.move_number()), ((1, 1), 2));
+        assert_eq!(game.display_text(2_000)[0], "Red    #2   Blue");
+        for now in (3_000..200_000).step_by(1_000) {
+            game.handle(PRESS_BLUE, now);
+            game.handle(PRESS_RED, now);
+        }
+        assert_eq!(game.display_text(200_000)[0], "Red   #199  Blue");
+
+        // byo-yomi periods are shown after the times, leaving the header to the number
+        let mut game = game_in(GameStatus::Paused);
+        game.red_player.time_control = TimeControl::ByoYomi;
+        game.blue_player.time_control = TimeControl::ByoYomi;
+        assert_eq!(
+            game.display_text(0),
+            ["Red    #1   Blue", "10:00(5)10:00(5)"]
+        );
+    }
+
+    /// Display which can show players' times on their own, recording what it was last asked to show.
+    #[derive(Default)]
+    struct TimesDisplay {
+        text: Option<(std::string::String, std::string::String)>,
+        times: Option<(i32, i32)>,
+    }
+
+    impl ClockDisplay for TimesDisplay {
+        type Error = core::convert::Infallible;
+
+        fn show(&mut self, header: &str, status: &str) -> Result<(), Self::Error> {
+            *self = TimesDisplay::default();
+            self.text = Some((header.into(), status.into()));
+            Ok(())
+        }
+
+        fn show_times(
+            &mut self,
+            red_millis: i32,
+            blue_millis: i32,
+            _tenths_millis: i32,
+        ) -> Result<bool, Self::Error> {
+            *self = TimesDisplay::default();
+            self.times = Some((red_millis, blue_millis));
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn only_times_shown_during_plain_play() {
+        let mut display = TimesDisplay::default();
+        let mut active = game_in(GameStatus::Active);
+        active.render(&mut display, 1_000).unwrap();
+        assert_eq!(display.times, Some((600_999, 599_999)));
+        active.handle(PRESS_YELLOW, 1_000);
+        active.render(&mut display, 1_000).unwrap();
+        assert_eq!(display.times, None);
+        assert!(display.text.is_some());
+
+        // stage and overtime tags need the text display
+        let mut byo_yomi = game(TimeControl::ByoYomi);
+        byo_yomi.handle(PRESS_RED, 0);
+        byo_yomi.render(&mut display, 1_000).unwrap();
+        assert_eq!(display.times, None);
+    }
+}
@@ -0,0 +1,59 @@
+//! Hardware-independent chess clock logic.
+//!
+//! The firmware (or any other front end) feeds button events and timer ticks into the [`Game`],
+//! along with the current time, and applies the LED/LCD actions it returns.
+#![cfg_attr(not(test), no_std)]
+
+pub mod big_digits;
+mod button;
+mod char_lcd;
+pub mod display;
+mod game;
+mod menu;
+mod player;
+pub mod presets;
+mod settings;
+mod sound;
+mod storage;
+pub mod time_control;
+
+pub use button::{Button, HOLD_MILLIS};
+pub use char_lcd::{CharLcd, I2cBus};
+pub use display::{CharacterLcd, ClockDisplay, FrameBuffer, ShadowLcd, DISPLAY_COLUMNS};
+pub use game::{Action, Actions, Event, Field, Game, GameStatus, Setting};
+pub use player::formatted_time;
+pub use settings::{PlayerSettings, Settings, TimeSettings, RECORD_BYTES};
+pub use sound::{Melody, Note, Sound};
+pub use storage::{Flash, SettingsStore, SECTOR_BYTES, STORAGE_BYTES};
+
+pub(crate) const SECS_TO_MILLIS: i32 = 1000;
+pub(crate) const MINS_TO_MILLIS: i32 = 60 * SECS_TO_MILLIS;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ButtonEvent {
+    Pressed(Color),
+    /// Button held down (see [`HOLD_MILLIS`]).
+    Held(Color),
+    /// Button still held down, repeated every HOLD_MILLIS after it was first held.
+    Repeated(Color),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Color {
+    Red,
+    Yellow,
+    Blue,
+}
+
+impl Color {
+    /// Returns the color's name, as shown on the LCD.
+    pub fn name(self) -> &'static str {
+        match self {
+            Color::Red => "Red",
+            Color::Yellow => "Yellow",
+            Color::Blue => "Blue",
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//! Settings menu, opened by holding Yellow during the pre-game phase.
+//!
+//! Red/Blue select the previous/next entry, and Yellow opens it: a nested menu is entered, and a
+//! value is edited with Red/Blue (press: 1 step, hold: 5) until Yellow is pressed again
+//! (stepping through the fields of a time first, e.g. hours, minutes then seconds), confirming it.
+//! "Back" returns to the enclosing menu, and "Exit" closes the menu.
+use core::fmt::Write;
+use heapless::{String, Vec};
+
+use crate::game::{Field, Setting};
+use crate::{ButtonEvent, Color};
+
+/// Number of levels of nested menus.
+const MAX_DEPTH: usize = 2;
+
+/// Setting shown (and adjusted) by a menu entry.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Value {
+    /// Pre-game setting, applied to both players.
+    Setting(Setting),
+    Sound,
+    Backlight,
+    /// Low-time warning threshold.
+    Warning,
+    /// Threshold below which times are shown in tenths of a second.
+    Tenths,
+}
+
+/// Entry of the settings menu.
+pub(crate) enum Entry {
+    /// Nested menu, with its title and entries.
+    Menu(&'static str, &'static [Entry]),
+    /// Value, with its label.
+    Value(&'static str, Value),
+    /// Return to the enclosing menu ("Back"), or close the menu from the top level ("Exit").
+    Back,
+}
+
+/// Top-level entries of the settings menu.
+pub(crate) const MENU: &[Entry] = &[
+    Entry::Menu(
+        "Time control",
+        &[
+            Entry::Value("Type", Value::Setting(Setting::Mode)),
+            Entry::Value("Time", Value::Setting(Setting::Time)),
+            Entry::Value("Bonus", Value::Setting(Setting::Bonus)),
+            Entry::Value("Flag fall", Value::Setting(Setting::Flag)),
+            Entry::Value("Players", Value::Setting(Setting::Link)),
+            Entry::Back,
+        ],
+    ),
+    Entry::Value("Sound", Value::Sound),
+    Entry::Value("Backlight", Value::Backlight),
+    Entry::Value("Low time", Value::Warning),
+    Entry::Value("Tenths below", Value::Tenths),
+    Entry::Back,
+];
+
+/// Result of a button event in the menu, to be applied by the game.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Outcome {
+    /// Another entry was selected (or opened): only the display changes.
+    Moved,
+    /// Adjust the given value by the specified step (of the given field, for times).
+    Adjust(Value, Field, i32),
+    /// The value being edited was confirmed.
+    Confirmed,
+    /// The menu was closed.
+    Closed,
+}
+
+/// Position in the settings menu.
+pub(crate) struct Menu {
+    /// Index of the selected entry in each open menu, from the top level down.
+    path: Vec<usize, MAX_DEPTH>,
+    /// Whether the selected value is being edited.
+    editing: bool,
+    /// Field of the value being edited, if it is a time.
+    field: Field,
+}
+
+impl Menu {
+    /// Returns the menu, opened at its first entry.
+    pub(crate) fn new() -> Menu {
+        let mut path = Vec::new();
+        path.push(0).unwrap();
+        Menu {
+            path,
+            editing: false,
+            field: Field::Hours,
+        }
+    }
+
+    /// Returns the entries of the innermost open menu.
+    fn entries(&self) -> &'static [Entry] {
+        let mut entries = MENU;
+        for index in &self.path[..self.path.len() - 1] {
+            if let Entry::Menu(_, nested) = entries[*index] {
+                entries = nested;
+            }
+        }
+        entries
+    }
+
+    fn selected(&self) -> &'static Entry {
+        &self.entries()[*self.path.last().unwrap()]
+    }
+
+    /// Move through the menu in response to the given button event.
+    /// Holding Yellow does nothing, as the hold which opened the menu may still be repeating
+    /// (and holds of Red/Blue repeat alike).
+    pub(crate) fn handle(&mut self, event: ButtonEvent) -> Outcome {
+        if self.editing {
+            return match event {
+                ButtonEvent::Pressed(Color::Yellow) => match self.field.next_in(self.fields()) {
+                    Some(field) => {
+                        self.field = field;
+                        Outcome::Moved
+                    }
+                    None => {
+                        self.editing = false;
+                        Outcome::Confirmed
+                    }
+                },
+                ButtonEvent::Held(Color::Yellow) | ButtonEvent::Repeated(Color::Yellow) => {
+                    Outcome::Moved
+                }
+                ButtonEvent::Pressed(_) => Outcome::Adjust(self.value(), self.field, 1),
+                ButtonEvent::Held(_) | ButtonEvent::Repeated(_) => {
+                    Outcome::Adjust(self.value(), self.field, 5)
+                }
+            };
+        }
+        let count = self.entries().len();
+        let index = self.path.last_mut().unwrap();
+        match event {
+            ButtonEvent::Pressed(Color::Red)
+            | ButtonEvent::Held(Color::Red)
+            | ButtonEvent::Repeated(Color::Red) => *index = (*index + count - 1) % count,
+            ButtonEvent::Pressed(Color::Blue)
+            | ButtonEvent::Held(Color::Blue)
+            | ButtonEvent::Repeated(Color::Blue) => *index = (*index + 1) % count,
+            ButtonEvent::Pressed(Color::Yellow) => match self.selected() {
+                Entry::Menu(..) => self.path.push(0).unwrap(),
+                Entry::Value(..) => {
+                    self.editing = true;
+                    self.field = self.fields().first().copied().unwrap_or(Field::Hours);
+                }
+                Entry::Back if self.path.len() == 1 => return Outcome::Closed,
+                Entry::Back => {
+                    self.path.pop();
+                }
+            },
+            ButtonEvent::Held(Color::Yellow) | ButtonEvent::Repeated(Color::Yellow) => (),
+        }
+        Outcome::Moved
+    }
+
+    /// Returns the value being edited.
+    fn value(&self) -> Value {
+        match self.selected() {
+            Entry::Value(_, value) => *value,
+            _ => unreachable!("only values are edited"),
+        }
+    }
+
+    /// Returns the fields of the value being edited (none unless it is a time).
+    fn fields(&self) -> &'static [Field] {
+        match self.value() {
+            Value::Setting(setting) => Field::of(setting),
+            Value::Sound | Value::Backlight | Value::Warning | Value::Tenths => &[],
+        }
+    }
+
+    /// Returns the two rows of text to be shown on the LCD: the selected entry's label, over its
+    /// value (as given by `value_text`), shown in brackets while it is being edited.
+    /// While a time is being edited, the label is followed by the field being edited.
+    pub(crate) fn display_text(&self, value_text: impl Fn(Value) -> String<16>) -> [String<32>; 2] {
+        let mut header: String<32> = String::new();
+        let mut buf: String<32> = String::new();
+        let mut value: String<16> = String::new();
+        let mut label: String<16> = String::new();
+        match self.selected() {
+            Entry::Menu(title, _) => {
+                value.push_str("...").unwrap();
+                label.push_str(title).unwrap();
+            }
+            Entry::Value(name, setting) => {
+                label.push_str(name).unwrap();
+                if !self.editing {
+                    value = value_text(*setting);
+                } else {
+                    core::write!(&mut value, "[{}]", value_text(*setting)).unwrap();
+                    if !self.fields().is_empty() {
+                        core::write!(&mut label, ": {}", self.field.label()).unwrap();
+                    }
+                }
+            }
+            Entry::Back if self.path.len() == 1 => label.push_str("Exit").unwrap(),
+            Entry::Back => label.push_str("Back").unwrap(),
+        }
+        core::write!(&mut header, "{:^16}", label).unwrap();
+        core::write!(&mut buf, "{:^16}", value).unwrap();
+        [header, buf]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRESS_RED: ButtonEvent = ButtonEvent::Pressed(Color::Red);
+    const PRESS_YELLOW: ButtonEvent = ButtonEvent::Pressed(Color::Yellow);
+    const PRESS_BLUE: ButtonEvent = ButtonEvent::Pressed(Color::Blue);
+    const HOLD_YELLOW: ButtonEvent = ButtonEvent::Held(Color::Yellow);
+    const HOLD_BLUE: ButtonEvent = ButtonEvent::Held(Color::Blue);
+
+    fn text(menu: &Menu) -> [String<32>; 2] {
+        menu.display_text(|_| String::from("On"))
+    }
+
+    #[test]
+    fn entries_wrap_around_and_fit_the_lcd() {
+        let mut menu = Menu::new();
+        assert_eq!(text(&menu), ["  Time control  ", "      ...       "]);
+        menu.handle(PRESS_RED);
+        assert_eq!(text(&menu), ["      Exit      ", "                "]);
+        menu.handle(HOLD_BLUE);
+        menu.handle(PRESS_BLUE);
+        assert_eq!(text(&menu), ["     Sound      ", "       On       "]);
+
+        fn check(entries: &[Entry]) {
+            for entry in entries {
+                match entry {
+                    Entry::Menu(title, nested) => {
+                        assert!(title.len() <= 16);
+                        check(nested);
+                    }
+                    Entry::Value(label, _) => assert!(label.len() <= 16),
+                    Entry::Back => (),
+                }
+            }
+        }
+        check(MENU);
+    }
+
+    #[test]
+    fn nested_menus_open_and_go_back() {
+        let mut menu = Menu::new();
+        assert_eq!(menu.handle(PRESS_YELLOW), Outcome::Moved);
+        assert_eq!(text(&menu)[0], "      Type      ");
+        menu.handle(PRESS_RED);
+        assert_eq!(text(&menu)[0], "      Back      ");
+        menu.handle(PRESS_YELLOW);
+        assert_eq!(text(&menu)[0], "  Time control  ");
+        menu.handle(PRESS_RED);
+        assert_eq!(menu.handle(PRESS_YELLOW), Outcome::Closed);
+    }
+
+    #[test]
+    fn values_are_adjusted_while_editing() {
+        let mut menu = Menu::new();
+        menu.handle(PRESS_YELLOW);
+        assert_eq!(menu.handle(PRESS_BLUE), Outcome::Moved);
+        menu.handle(PRESS_YELLOW);
+        assert_eq!(text(&menu), ["  Time: Hours   ", "      [On]      "]);
+        let time = Value::Setting(Setting::Time);
+        assert_eq!(
+            menu.handle(PRESS_RED),
+            Outcome::Adjust(time, Field::Hours, 1)
+        );
+        assert_eq!(
+            menu.handle(HOLD_BLUE),
+            Outcome::Adjust(time, Field::Hours, 5)
+        );
+        // a repeated hold of Yellow doesn't leave the value
+        assert_eq!(menu.handle(HOLD_YELLOW), Outcome::Moved);
+        assert_eq!(
+            menu.handle(ButtonEvent::Repeated(Color::Yellow)),
+            Outcome::Moved
+        );
+        menu.handle(PRESS_YELLOW);
+        assert_eq!(text(&menu)[0], " Time: Minutes  ");
+        assert_eq!(
+            menu.handle(PRESS_BLUE),
+            Outcome::Adjust(time, Field::Minutes, 1)
+        );
+        menu.handle(PRESS_YELLOW);
+        assert_eq!(menu.handle(PRESS_YELLOW), Outcome::Confirmed);
+        assert_eq!(text(&menu), ["      Time      ", "       On       "]);
+
+        // other values have no fields
+        menu.handle(PRESS_BLUE);
+        menu.handle(PRESS_BLUE);
+        menu.handle(PRESS_YELLOW);
+        assert_eq!(text(&menu), ["   Flag fall    ", "      [On]      "]);
+        menu.handle(PRESS_YELLOW);
+        assert_eq!(text(&menu), ["   Flag fall    ", "       On       "]);
+    }
+}
@@ -0,0 +1,530 @@
+use core::fmt::Write;
+use heapless::String;
+
+use crate::game::{Field, Setting};
+use crate::settings::PlayerSettings;
+use crate::time_control::{Overtime, StagePlan, TimeControl, STAGE_PLANS};
+use crate::{DISPLAY_COLUMNS, MINS_TO_MILLIS, SECS_TO_MILLIS};
+
+pub(crate) const TRUNCATION_OFFSET_MILLIS: i32 = 999; // offset by 999 millis to account for truncation
+const DEFAULT_TURN_MILLIS: i32 = 10 * MINS_TO_MILLIS + TRUNCATION_OFFSET_MILLIS;
+const MAX_HOURS: i32 = 9;
+const MAX_PERIODS: u8 = 10;
+const MAX_BLOCK_MOVES: u8 = 30;
+pub(crate) const DEFAULT_OVERTIME: Overtime = Overtime {
+    periods: 5,
+    block_moves: 10,
+    moves_left: 0,
+    active: false,
+};
+
+/// Controls individual player state.
+/// Times (`now`) are in millis since an arbitrary fixed point in time (e.g. boot).
+pub struct Player {
+    pub(crate) millis_left: i32,
+    pub(crate) time_control: TimeControl,
+    pub(crate) bonus_millis: i32,
+    pub(crate) stage_plan: usize,
+    pub(crate) stage: usize,
+    pub(crate) overtime: Overtime,
+    /// Moves completed, counted as each turn ends (see [`Player::end_turn`]).
+    pub(crate) moves: u16,
+    pub(crate) is_active: bool,
+    pub(crate) time_activated: Option<u64>,
+    /// Time elapsed in the turn in progress before the game was last paused, carried over as the
+    /// turn resumes (so that delays apply to the turn as a whole).
+    pub(crate) paused_elapsed: i32,
+}
+
+impl Player {
+    pub fn new() -> Player {
+        Player {
+            millis_left: DEFAULT_TURN_MILLIS,
+            time_control: TimeControl::Increment,
+            bonus_millis: 0,
+            stage_plan: 0,
+            stage: 0,
+            overtime: DEFAULT_OVERTIME,
+            moves: 0,
+            is_active: false,
+            time_activated: None,
+            paused_elapsed: 0,
+        }
+    }
+
+    /// Reduce the given field (hours, minutes or seconds) of player's turn time by the specified
+    /// step, wrapping around within the field (skipping a time of zero).
+    /// Used during the "pre-game" phase to select player time limits.
+    fn decrement_time(&mut self, field: Field, step: i32) {
+        let mut millis = step_field(self.millis_left, field, -step);
+        if millis == 0 {
+            millis = step_field(millis, field, -1);
+        }
+        self.millis_left = millis + TRUNCATION_OFFSET_MILLIS;
+    }
+
+    /// Set player's turn time to the given share (`parts` in `of_parts`) of their opponent's time
+    /// (`millis`), in whole seconds (at least one, at most the longest time which can be set).
+    /// Used during the "pre-game" phase to keep players' times at odds (see [`crate::time_control::Link`]).
+    pub(crate) fn set_time_share(&mut self, millis: i32, parts: u8, of_parts: u8) {
+        let secs = millis / SECS_TO_MILLIS * parts as i32 / of_parts as i32;
+        let secs = secs.clamp(1, (MAX_HOURS + 1) * 3600 - 1);
+        self.millis_left = secs * SECS_TO_MILLIS + TRUNCATION_OFFSET_MILLIS;
+    }
+
+    /// Increase the given field (minutes or seconds) of player's bonus (increment, delay or
+    /// overtime) by the specified step, wrapping around within the field.
+    /// Used during the "pre-game" phase to select player bonuses.
+    fn increase_bonus(&mut self, field: Field, step: i32) {
+        self.bonus_millis = step_field(self.bonus_millis, field, step);
+    }
+
+    /// Increase player's number of byo-yomi periods (or moves per Canadian overtime block)
+    /// by the specified step. Used during the "pre-game" phase; wraps back to one past the maximum.
+    fn increase_overtime(&mut self, step: u8) {
+        let (value, max) = match self.time_control {
+            TimeControl::Canadian => (&mut self.overtime.block_moves, MAX_BLOCK_MOVES),
+            _ => (&mut self.overtime.periods, MAX_PERIODS),
+        };
+        if *value + step <= max {
+            *value += step;
+        } else {
+            *value = 1;
+        }
+    }
+
+    /// Select the player's staged time control, setting their time to that of its first stage
+    /// (leaving it as selected for a single stage).
+    pub(crate) fn set_stage_plan(&mut self, stage_plan: usize) {
+        self.stage_plan = stage_plan;
+        if let Some(stage) = STAGE_PLANS[stage_plan].stages.first() {
+            self.millis_left = stage.millis + TRUNCATION_OFFSET_MILLIS;
+        }
+    }
+
+    /// Adjust the given pre-game setting by the specified step, in units of the given field
+    /// for times. The time control mode, stages and flag behavior are shared by both players
+    /// (see `Game::adjust_setting`).
+    pub(crate) fn adjust(&mut self, setting: Setting, field: Field, step: i32) {
+        match setting {
+            Setting::Time => self.decrement_time(field, step),
+            Setting::Preset | Setting::Link | Setting::Mode | Setting::Stages | Setting::Flag => (),
+            Setting::Bonus => self.increase_bonus(field, step),
+            Setting::Overtime => self.increase_overtime(step as u8),
+        }
+    }
+
+    /// Returns the player's pre-game settings.
+    pub(crate) fn settings(&self) -> PlayerSettings {
+        PlayerSettings {
+            millis: self.millis_left,
+            bonus_millis: self.bonus_millis,
+            periods: self.overtime.periods,
+            block_moves: self.overtime.block_moves,
+        }
+    }
+
+    /// Apply the given pre-game settings (e.g. as restored from flash).
+    pub(crate) fn apply(&mut self, settings: PlayerSettings) {
+        self.millis_left = settings.millis;
+        self.bonus_millis = settings.bonus_millis;
+        self.overtime.periods = settings.periods;
+        self.overtime.block_moves = settings.block_moves;
+    }
+
+    /// Returns player's staged time control.
+    pub(crate) fn plan(&self) -> &'static StagePlan {
+        &STAGE_PLANS[self.stage_plan]
+    }
+
+    /// Returns player's bonus (increment, delay or overtime) as a formatted string.
+    /// Format: M:SS
+    pub(crate) fn formatted_bonus(&self) -> String<32> {
+        let secs = self.bonus_millis / SECS_TO_MILLIS;
+        let mut buf: String<32> = String::new();
+        core::write!(&mut buf, "{}:{:>02}", secs / 60, secs % 60).unwrap();
+        buf
+    }
+
+    /// Returns time elapsed in the turn in progress, including any time before it was paused.
+    fn elapsed(&self, now: u64) -> i32 {
+        match self.time_activated {
+            Some(time_activated) => self.paused_elapsed + now.saturating_sub(time_activated) as i32,
+            None => self.paused_elapsed,
+        }
+    }
+
+    /// Returns time charged against the player's clock for the turn in progress (zero if none),
+    /// since it was last started or resumed.
+    pub(crate) fn time_used(&self, now: u64) -> i32 {
+        if self.time_activated.is_none() {
+            return 0;
+        }
+        self.time_control
+            .charged_millis(self.elapsed(now), self.bonus_millis)
+            - self
+                .time_control
+                .charged_millis(self.paused_elapsed, self.bonus_millis)
+    }
+
+    /// Applies stage and overtime rules to the given time remaining, without updating the player.
+    /// Returns the resulting time remaining, stage and overtime state.
+    fn settle(&self, millis_left: i32) -> (i32, usize, Overtime) {
+        let (millis_left, stage) = self.plan().after_flag(millis_left, self.stage);
+        let (millis_left, overtime) =
+            self.time_control
+                .after_flag(millis_left, self.overtime, self.bonus_millis);
+        (millis_left, stage, overtime)
+    }
+
+    /// Returns player's current time remaining, stage and overtime state,
+    /// accounting for the turn in progress (if any).
+    fn current_state(&self, now: u64) -> (i32, usize, Overtime) {
+        self.settle(self.millis_left - self.time_used(now))
+    }
+
+    /// Returns player's current time remaining, accounting for the turn in progress (if any).
+    pub fn time_remaining(&self, now: u64) -> i32 {
+        self.current_state(now).0
+    }
+
+    /// Returns a short tag shown next to the player's name on the LCD:
+    /// the current stage, if the time control is staged.
+    pub(crate) fn status_tag(&self, now: u64) -> String<8> {
+        let (_, stage, _) = self.current_state(now);
+        let mut buf: String<8> = String::new();
+        if self.plan().is_staged() {
+            core::write!(&mut buf, "S{}", stage + 1).unwrap();
+        }
+        buf
+    }
+
+    /// Returns the given time remaining as shown on the player's side of the LCD,
+    /// in tenths of a second below the given threshold.
+    /// With byo-yomi, also shows the periods remaining, and within a Canadian overtime block,
+    /// the moves remaining in the block.
+    /// Format: as [`formatted_time`], with single-digit minutes and [-]M:SS (N) with byo-yomi
+    /// (without the space if it doesn't fit) or [-]M:SS/N in Canadian overtime
+    pub(crate) fn formatted_status(
+        &self,
+        millis_left: i32,
+        now: u64,
+        tenths_millis: i32,
+    ) -> String<32> {
+        let (_, _, overtime) = self.current_state(now);
+        let mut buf: String<32> = String::new();
+        match self.time_control {
+            TimeControl::ByoYomi => {
+                write_time(&mut buf, millis_left, 1, tenths_millis);
+                let mut periods: String<8> = String::new();
+                core::write!(&mut periods, "({})", overtime.periods).unwrap();
+                if buf.len() + periods.len() < DISPLAY_COLUMNS / 2 {
+                    buf.push(' ').unwrap();
+                }
+                buf.push_str(&periods).unwrap();
+            }
+            TimeControl::Canadian if overtime.active => {
+                write_time(&mut buf, millis_left, 1, tenths_millis);
+                core::write!(&mut buf, "/{}", overtime.moves_left).unwrap();
+            }
+            _ => return formatted_time(millis_left, tenths_millis),
+        }
+        buf
+    }
+
+    /// Initiate player's turn, or resume it if it was paused.
+    pub(crate) fn start_turn(&mut self, now: u64) {
+        if !self.is_active {
+            self.is_active = true;
+            self.time_activated = Some(now);
+        }
+    }
+
+    /// End player's turn (completing a move) and update time remaining according to the time control.
+    /// Returns the time charged against the player's clock for the turn.
+    pub(crate) fn end_turn(&mut self, now: u64) -> i32 {
+        let mut time_used = 0;
+        if self.is_active {
+            self.is_active = false;
+            if self.time_activated.is_some() {
+                let elapsed = self.elapsed(now);
+                time_used = self.time_used(now);
+                let (millis_left, stage, overtime) = self.settle(self.millis_left - time_used);
+                let millis_left = millis_left
+                    + self
+                        .time_control
+                        .credited_millis(elapsed, self.bonus_millis);
+                self.moves += 1;
+                let (millis_left, stage) = self.plan().after_move(millis_left, stage, self.moves);
+                (self.millis_left, self.overtime) =
+                    self.time_control
+                        .after_move(millis_left, overtime, self.bonus_millis);
+                self.stage = stage;
+                self.time_activated = None;
+                self.paused_elapsed = 0;
+            };
+        }
+        time_used
+    }
+
+    /// Stop player's clock without completing a move (i.e. when the game is paused).
+    /// The time elapsed is kept towards the turn's delay, should it be resumed.
+    /// Returns the time charged against the player's clock for the turn.
+    pub(crate) fn pause_turn(&mut self, now: u64) -> i32 {
+        let time_used = self.time_used(now);
+        if self.is_active {
+            self.is_active = false;
+            (self.millis_left, self.stage, self.overtime) =
+                self.settle(self.millis_left - time_used);
+            self.paused_elapsed = self.elapsed(now);
+            self.time_activated = None;
+        }
+        time_used
+    }
+
+    /// Drop the paused turn (if any) without completing a move, as the opponent's clock is
+    /// started instead.
+    pub(crate) fn drop_turn(&mut self) {
+        self.paused_elapsed = 0;
+    }
+
+    /// Add time drained from the opponent's clock, if playing in hourglass mode.
+    pub(crate) fn credit_hourglass(&mut self, millis: i32) {
+        if self.time_control == TimeControl::Hourglass {
+            self.millis_left += millis;
+        }
+    }
+
+    /// Reset player's state to initiate a new game.
+    pub(crate) fn reset(&mut self) {
+        self.millis_left = DEFAULT_TURN_MILLIS;
+        self.time_control = TimeControl::Increment;
+        self.bonus_millis = 0;
+        self.stage_plan = 0;
+        self.stage = 0;
+        self.overtime = DEFAULT_OVERTIME;
+        self.moves = 0;
+        self.is_active = false;
+        self.time_activated = None;
+        self.paused_elapsed = 0;
+    }
+}
+
+/// Step the given field of a time (in millis) by the specified amount, wrapping around within
+/// the field (e.g. 0-59 minutes) and leaving the other fields unchanged.
+/// Returns the new time in whole seconds (as millis).
+fn step_field(millis: i32, field: Field, step: i32) -> i32 {
+    let secs = millis / SECS_TO_MILLIS;
+    let (unit, range) = match field {
+        Field::Hours => (3600, MAX_HOURS + 1),
+        Field::Minutes => (60, 60),
+        Field::Seconds => (1, 60),
+    };
+    let value = secs / unit % range;
+    let stepped = (value + step).rem_euclid(range);
+    (secs + (stepped - value) * unit) * SECS_TO_MILLIS
+}
+
+/// Returns the given time setting (e.g. a player's starting time) as a formatted string.
+/// Format: H:MM:SS
+pub(crate) fn formatted_setting(millis: i32) -> String<32> {
+    let secs = millis / SECS_TO_MILLIS;
+    let mut buf: String<32> = String::new();
+    core::write!(
+        &mut buf,
+        "{}:{:>02}:{:>02}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+    .unwrap();
+    buf
+}
+
+/// Returns the given time remaining as a formatted string.
+/// Format: [-]MM:SS ([-]H:MM:SS from an hour), or [S]S.s when under the given threshold
+/// (see [`crate::Settings::tenths_secs`])
+pub fn formatted_time(millis_left: i32, tenths_millis: i32) -> String<32> {
+    let mut buf: String<32> = String::new();
+    write_time(&mut buf, millis_left, 2, tenths_millis);
+    buf
+}
+
+/// Write the given time remaining to the buffer (see [`formatted_time`]), with minutes
+/// zero-padded to the given width below an hour.
+fn write_time(buf: &mut String<32>, millis_left: i32, minutes_width: usize, tenths_millis: i32) {
+    if (0..tenths_millis).contains(&millis_left) {
+        let secs = millis_left / 1000;
+        let tenths = millis_left % 1000 / 100;
+        core::write!(buf, "{}.{}", secs, tenths).unwrap();
+        return;
+    }
+    let sign = if millis_left < 0 { "-" } else { "" };
+    let mins = millis_left.abs() / (MINS_TO_MILLIS);
+    let secs = millis_left.abs() % (MINS_TO_MILLIS) / 1000;
+    if mins >= 60 {
+        core::write!(buf, "{}{}:{:>02}:{:>02}", sign, mins / 60, mins % 60, secs).unwrap();
+    } else {
+        core::write!(buf, "{}{:>0minutes_width$}:{:>02}", sign, mins, secs).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TENTHS: i32 = 10 * SECS_TO_MILLIS;
+
+    fn player(time_control: TimeControl, bonus_millis: i32) -> Player {
+        let mut player = Player::new();
+        player.millis_left = 60_000;
+        player.time_control = time_control;
+        player.bonus_millis = bonus_millis;
+        player
+    }
+
+    #[test]
+    fn formatted_time_shows_minutes_and_seconds() {
+        assert_eq!(formatted_time(DEFAULT_TURN_MILLIS, TENTHS), "10:00");
+        assert_eq!(formatted_time(61_500, TENTHS), "01:01");
+        assert_eq!(formatted_time(-61_500, TENTHS), "-01:01");
+        assert_eq!(formatted_time(90 * 60_000 + 999, TENTHS), "1:30:00");
+    }
+
+    #[test]
+    fn formatted_time_shows_tenths_under_threshold() {
+        assert_eq!(formatted_time(10_000, 10_000), "00:10");
+        assert_eq!(formatted_time(9_999, 10_000), "9.9");
+        assert_eq!(formatted_time(420, 10_000), "0.4");
+        assert_eq!(formatted_time(0, 10_000), "0.0");
+        assert_eq!(formatted_time(20_000, 20_000), "00:20");
+        assert_eq!(formatted_time(19_999, 20_000), "19.9");
+        assert_eq!(formatted_time(10_000, 20_000), "10.0");
+    }
+
+    #[test]
+    fn canadian_overtime_status_shows_moves_left() {
+        let mut player = player(TimeControl::Canadian, 60_000);
+        assert_eq!(player.formatted_status(61_500, 0, TENTHS), "01:01");
+        player.overtime.active = true;
+        player.overtime.moves_left = 3;
+        assert_eq!(player.formatted_status(61_500, 0, TENTHS), "1:01/3");
+        assert_eq!(player.formatted_status(-61_500, 0, TENTHS), "-1:01/3");
+        assert_eq!(player.formatted_status(5_000, 0, TENTHS), "5.0/3");
+    }
+
+    #[test]
+    fn byo_yomi_status_shows_periods_left() {
+        let mut player = player(TimeControl::ByoYomi, 60_000);
+        player.overtime.periods = 3;
+        assert_eq!(player.formatted_status(300_500, 0, TENTHS), "5:00 (3)");
+        assert_eq!(player.formatted_status(5_000, 0, TENTHS), "5.0 (3)");
+        // without the space if it doesn't fit
+        assert_eq!(player.formatted_status(600_500, 0, TENTHS), "10:00(3)");
+        player.overtime.periods = 10;
+        assert_eq!(player.formatted_status(300_500, 0, TENTHS), "5:00(10)");
+    }
+
+    #[test]
+    fn time_fields_wrap_within_their_range() {
+        let mut player = Player::new();
+        player.adjust(Setting::Time, Field::Hours, 1);
+        assert_eq!(formatted_setting(player.millis_left), "9:10:00");
+        assert_eq!(player.millis_left % 1000, TRUNCATION_OFFSET_MILLIS);
+        player.adjust(Setting::Time, Field::Minutes, 15);
+        assert_eq!(formatted_setting(player.millis_left), "9:55:00");
+        player.adjust(Setting::Time, Field::Seconds, 1);
+        assert_eq!(formatted_setting(player.millis_left), "9:55:59");
+
+        // a time of zero is skipped
+        player.millis_left = 5 * 1000 + TRUNCATION_OFFSET_MILLIS;
+        player.adjust(Setting::Time, Field::Seconds, 5);
+        assert_eq!(formatted_setting(player.millis_left), "0:00:59");
+    }
+
+    #[test]
+    fn time_share_is_in_whole_seconds_and_in_range() {
+        let mut player = Player::new();
+        player.set_time_share(5 * 60_000 + 999, 1, 5);
+        assert_eq!(formatted_setting(player.millis_left), "0:01:00");
+        assert_eq!(player.millis_left % 1000, TRUNCATION_OFFSET_MILLIS);
+        player.set_time_share(7_999, 1, 10);
+        assert_eq!(formatted_setting(player.millis_left), "0:00:01");
+        player.set_time_share(5 * 3_600_000, 10, 1);
+        assert_eq!(formatted_setting(player.millis_left), "9:59:59");
+    }
+
+    #[test]
+    fn bonus_fields_wrap_within_their_range() {
+        let mut player = player(TimeControl::Canadian, 0);
+        player.adjust(Setting::Bonus, Field::Minutes, 5);
+        assert_eq!(player.formatted_bonus(), "5:00");
+        player.adjust(Setting::Bonus, Field::Seconds, 55);
+        player.adjust(Setting::Bonus, Field::Seconds, 5);
+        assert_eq!(player.formatted_bonus(), "5:00");
+        player.adjust(Setting::Bonus, Field::Minutes, 55);
+        assert_eq!(player.bonus_millis, 0);
+    }
+
+    #[test]
+    fn turn_charges_time_used() {
+        let mut player = player(TimeControl::Increment, 0);
+        player.start_turn(1_000);
+        assert!(player.is_active);
+        assert_eq!(player.time_remaining(4_000), 57_000);
+        assert_eq!(player.end_turn(4_000), 3_000);
+        assert!(!player.is_active);
+        assert_eq!(player.millis_left, 57_000);
+        assert_eq!(player.moves, 1);
+    }
+
+    #[test]
+    fn increment_credited_on_end_turn() {
+        let mut player = player(TimeControl::Increment, 2_000);
+        player.start_turn(0);
+        player.end_turn(5_000);
+        assert_eq!(player.millis_left, 57_000);
+    }
+
+    #[test]
+    fn delay_holds_clock_during_turn() {
+        let mut player = player(TimeControl::Delay, 5_000);
+        player.start_turn(0);
+        assert_eq!(player.time_remaining(4_000), 60_000);
+        assert_eq!(player.time_remaining(7_000), 58_000);
+        player.end_turn(7_000);
+        assert_eq!(player.millis_left, 58_000);
+    }
+
+    #[test]
+    fn pause_does_not_count_as_move() {
+        let mut player = player(TimeControl::Increment, 2_000);
+        player.start_turn(0);
+        assert_eq!(player.pause_turn(5_000), 5_000);
+        assert_eq!(player.millis_left, 55_000);
+        assert_eq!(player.moves, 0);
+    }
+
+    #[test]
+    fn bronstein_credits_time_used_before_pausing() {
+        let mut player = player(TimeControl::Bronstein, 5_000);
+        player.start_turn(0);
+        assert_eq!(player.pause_turn(3_000), 3_000);
+        player.start_turn(10_000);
+        player.end_turn(11_000);
+        assert_eq!(player.millis_left, 60_000);
+    }
+
+    #[test]
+    fn delay_is_not_renewed_by_pausing() {
+        let mut player = player(TimeControl::Delay, 5_000);
+        player.start_turn(0);
+        assert_eq!(player.pause_turn(4_000), 0);
+        player.start_turn(10_000);
+        assert_eq!(player.time_remaining(14_000), 57_000);
+        assert_eq!(player.pause_turn(14_000), 3_000);
+        player.start_turn(20_000);
+        assert_eq!(player.end_turn(21_000), 1_000);
+        assert_eq!(player.millis_left, 56_000);
+    }
+}
@@ -0,0 +1,70 @@
+//! Named time controls, selected on the first pre-game page instead of adjusting each setting.
+use crate::player::{DEFAULT_OVERTIME, TRUNCATION_OFFSET_MILLIS};
+use crate::settings::{PlayerSettings, TimeSettings};
+use crate::time_control::TimeControl;
+use crate::{MINS_TO_MILLIS, SECS_TO_MILLIS};
+
+/// Number of presets which can be saved by the players (in addition to [`PRESETS`]).
+pub const MAX_CUSTOM_PRESETS: usize = 4;
+
+/// A named time control, applied to both players.
+pub struct Preset {
+    /// Name of the preset, as shown on the LCD (max 16 chars).
+    pub name: &'static str,
+    pub time: TimeSettings,
+}
+
+/// Built-in presets, cycled through with the Red/Blue buttons (followed by any custom presets).
+pub const PRESETS: [Preset; 7] = [
+    preset("Bullet 1+0", 0, 1, 0),
+    preset("Blitz 3+2", 0, 3, 2),
+    preset("Blitz 5+0", 0, 5, 0),
+    preset("Rapid 10+5", 0, 10, 5),
+    preset("Rapid 15+10", 0, 15, 10),
+    preset("Classical 90+30", 0, 90, 30),
+    // 90 minutes for 40 moves, then 30 minutes for the rest of the game (see STAGE_PLANS)
+    preset("FIDE 90/40+30", 1, 90, 30),
+];
+
+/// Returns a preset with a Fischer increment (in seconds), starting with the given minutes.
+const fn preset(name: &'static str, stage_plan: u8, minutes: i32, increment: i32) -> Preset {
+    let player = PlayerSettings {
+        millis: minutes * MINS_TO_MILLIS + TRUNCATION_OFFSET_MILLIS,
+        bonus_millis: increment * SECS_TO_MILLIS,
+        periods: DEFAULT_OVERTIME.periods,
+        block_moves: DEFAULT_OVERTIME.block_moves,
+    };
+    Preset {
+        name,
+        time: TimeSettings {
+            time_control: TimeControl::Increment,
+            stage_plan,
+            players: [player; 2],
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time_control::STAGE_PLANS;
+
+    #[test]
+    fn names_fit_the_lcd_and_presets_are_distinct() {
+        for (i, preset) in PRESETS.iter().enumerate() {
+            assert!(preset.name.len() <= 16, "{}", preset.name);
+            assert!(PRESETS[..i].iter().all(|other| other.time != preset.time));
+        }
+    }
+
+    #[test]
+    fn fide_preset_adds_thirty_minutes_after_move_forty() {
+        let fide = &PRESETS[6].time;
+        let plan = &STAGE_PLANS[fide.stage_plan as usize];
+        assert_eq!(plan.stages[0].moves, Some(40));
+        assert_eq!(plan.stages[0].millis, 90 * MINS_TO_MILLIS);
+        assert_eq!(plan.stages[1].millis, 30 * MINS_TO_MILLIS);
+        assert_eq!(fide.players[0].millis, 90 * MINS_TO_MILLIS + 999);
+        assert_eq!(fide.players[1].bonus_millis, 30 * SECS_TO_MILLIS);
+    }
+}
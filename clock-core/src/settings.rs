@@ -0,0 +1,481 @@
+use heapless::Vec;
+
+use crate::game::{TENTHS_SECS, WARNING_SECS};
+use crate::presets::MAX_CUSTOM_PRESETS;
+use crate::time_control::{FlagBehavior, Link, TimeControl, LINKS, STAGE_PLANS};
+
+/// Size of a stored settings record (see [`Settings::to_record`]), leaving room for new settings.
+pub const RECORD_BYTES: usize = 128;
+const MAGIC: [u8; 2] = *b"PC";
+/// Version of the record layout, bumped whenever the payload changes.
+/// (1: time settings and flag behavior; 2: added custom presets; 3: added sound and backlight;
+/// 4: added link between players; 5: added low-time warning threshold; 6: added tenths threshold)
+const VERSION: u8 = 6;
+/// Size of the records of version 1, which were stored in slots of this size.
+pub(crate) const V1_RECORD_BYTES: usize = 64;
+const HEADER_BYTES: usize = 8;
+const PLAYER_BYTES: usize = 10;
+const TIME_BYTES: usize = 2 + 2 * PLAYER_BYTES;
+const OPTIONS_OFFSET: usize = TIME_BYTES + 2 + MAX_CUSTOM_PRESETS * TIME_BYTES;
+const LINK_OFFSET: usize = OPTIONS_OFFSET + 1;
+const WARNING_OFFSET: usize = LINK_OFFSET + 1;
+const TENTHS_OFFSET: usize = WARNING_OFFSET + 1;
+const PAYLOAD_BYTES: usize = TENTHS_OFFSET + 1;
+/// Payload size of the records of each version (from 1). Each version appends its settings to
+/// those of the last, so that older records can still be read, with the settings they lack left
+/// at their defaults. Version 1 stored the flag behavior before the players' settings.
+const VERSION_PAYLOAD_BYTES: [usize; VERSION as usize] = [
+    TIME_BYTES + 1,
+    OPTIONS_OFFSET,
+    LINK_OFFSET,
+    WARNING_OFFSET,
+    TENTHS_OFFSET,
+    PAYLOAD_BYTES,
+];
+const CHECKSUM_BYTES: usize = 2;
+
+const TIME_CONTROLS: [TimeControl; 6] = [
+    TimeControl::Increment,
+    TimeControl::Delay,
+    TimeControl::Bronstein,
+    TimeControl::Hourglass,
+    TimeControl::ByoYomi,
+    TimeControl::Canadian,
+];
+const FLAG_BEHAVIORS: [FlagBehavior; 2] = [FlagBehavior::HardStop, FlagBehavior::CountNegative];
+// bits of the options byte
+const SOUND: u8 = 0x01;
+const BACKLIGHT: u8 = 0x02;
+
+/// Settings chosen during the pre-game phase, kept across power cycles (see [`crate::SettingsStore`]).
+#[derive(Clone, PartialEq, Debug)]
+pub struct Settings {
+    pub time: TimeSettings,
+    pub flag_behavior: FlagBehavior,
+    /// How players' settings are linked during the pre-game phase (e.g. time odds).
+    pub link: Link,
+    /// Presets saved by the players (see [`crate::presets`]).
+    pub custom_presets: Vec<TimeSettings, MAX_CUSTOM_PRESETS>,
+    /// Whether the clock makes sounds (if it has a buzzer).
+    pub sound: bool,
+    /// Whether the display's backlight is on.
+    pub backlight: bool,
+    /// Low-time warning threshold, in seconds (0: off).
+    pub warning_secs: u8,
+    /// Threshold below which times are shown in tenths of a second, in seconds (10 or 20).
+    pub tenths_secs: u8,
+}
+
+/// Time control of a game, as selected with a preset (see [`crate::presets`]).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TimeSettings {
+    pub time_control: TimeControl,
+    /// Index of the staged time control (see [`STAGE_PLANS`]).
+    pub stage_plan: u8,
+    /// Settings of each player (red, blue).
+    pub players: [PlayerSettings; 2],
+}
+
+/// Settings chosen by each player during the pre-game phase.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PlayerSettings {
+    /// Time at the start of the game.
+    pub millis: i32,
+    /// Increment, delay or overtime, depending on the time control.
+    pub bonus_millis: i32,
+    /// Byo-yomi periods.
+    pub periods: u8,
+    /// Moves per Canadian overtime block.
+    pub block_moves: u8,
+}
+
+impl Settings {
+    /// Returns the settings as a record to be stored, stamped with the given sequence number
+    /// (used to find the latest record). Format (little endian):
+    /// magic (2 bytes), version, payload length, sequence (4 bytes), payload, CRC-16 (2 bytes).
+    /// Unused bytes are left erased (0xFF).
+    pub(crate) fn to_record(&self, sequence: u32) -> [u8; RECORD_BYTES] {
+        let mut record = [0xFF; RECORD_BYTES];
+        record[..2].copy_from_slice(&MAGIC);
+        record[2] = VERSION;
+        record[3] = PAYLOAD_BYTES as u8;
+        record[4..HEADER_BYTES].copy_from_slice(&sequence.to_le_bytes());
+        let payload = &mut record[HEADER_BYTES..HEADER_BYTES + PAYLOAD_BYTES];
+        self.time.write(&mut payload[..TIME_BYTES]);
+        payload[TIME_BYTES] = FLAG_BEHAVIORS
+            .iter()
+            .position(|flag_behavior| *flag_behavior == self.flag_behavior)
+            .unwrap_or(0) as u8;
+        payload[TIME_BYTES + 1] = self.custom_presets.len() as u8;
+        let presets = payload[TIME_BYTES + 2..].chunks_exact_mut(TIME_BYTES);
+        for (bytes, preset) in presets.zip(&self.custom_presets) {
+            preset.write(bytes);
+        }
+        let mut options = 0;
+        if self.sound {
+            options |= SOUND;
+        }
+        if self.backlight {
+            options |= BACKLIGHT;
+        }
+        payload[OPTIONS_OFFSET] = options;
+        payload[LINK_OFFSET] = LINKS
+            .iter()
+            .position(|link| *link == self.link)
+            .unwrap_or(0) as u8;
+        payload[WARNING_OFFSET] = WARNING_SECS
+            .iter()
+            .position(|secs| *secs == self.warning_secs)
+            .unwrap_or(0) as u8;
+        payload[TENTHS_OFFSET] = TENTHS_SECS
+            .iter()
+            .position(|secs| *secs == self.tenths_secs)
+            .unwrap_or(0) as u8;
+        let end = HEADER_BYTES + PAYLOAD_BYTES;
+        let checksum = crc16(&record[..end]);
+        record[end..end + CHECKSUM_BYTES].copy_from_slice(&checksum.to_le_bytes());
+        record
+    }
+
+    /// Reads settings from a stored record (of any version), returning them with the record's
+    /// sequence number. Settings added since the record's version are left at their defaults.
+    /// Returns None if the record is erased, corrupted, of an unknown version or out of range.
+    pub(crate) fn from_record(record: &[u8]) -> Option<(u32, Settings)> {
+        let version = *record.get(2)?;
+        let payload_bytes = *VERSION_PAYLOAD_BYTES.get((version as usize).checked_sub(1)?)?;
+        let end = HEADER_BYTES + payload_bytes;
+        let checksum = record.get(end..end + CHECKSUM_BYTES)?;
+        if record[..2] != MAGIC
+            || record[3] as usize != payload_bytes
+            || crc16(&record[..end]).to_le_bytes() != checksum
+        {
+            return None;
+        }
+        let sequence = u32::from_le_bytes(record[4..HEADER_BYTES].try_into().ok()?);
+        let payload = &record[HEADER_BYTES..end];
+        if version == 1 {
+            return Some((sequence, Settings::from_v1_payload(payload)?));
+        }
+        let mut custom_presets = Vec::new();
+        let count = payload[TIME_BYTES + 1] as usize;
+        for bytes in payload[TIME_BYTES + 2..]
+            .chunks_exact(TIME_BYTES)
+            .take(count)
+        {
+            custom_presets.push(TimeSettings::read(bytes)?).ok()?;
+        }
+        let defaults = Settings::default();
+        let options = match payload.get(OPTIONS_OFFSET) {
+            Some(options) => *options,
+            None => SOUND | BACKLIGHT,
+        };
+        if custom_presets.len() != count || options & !(SOUND | BACKLIGHT) != 0 {
+            return None;
+        }
+        let settings = Settings {
+            time: TimeSettings::read(&payload[..TIME_BYTES])?,
+            flag_behavior: *FLAG_BEHAVIORS.get(payload[TIME_BYTES] as usize)?,
+            link: read_index(payload, LINK_OFFSET, &LINKS, defaults.link)?,
+            custom_presets,
+            sound: options & SOUND != 0,
+            backlight: options & BACKLIGHT != 0,
+            warning_secs: read_index(
+                payload,
+                WARNING_OFFSET,
+                &WARNING_SECS,
+                defaults.warning_secs,
+            )?,
+            tenths_secs: read_index(payload, TENTHS_OFFSET, &TENTHS_SECS, defaults.tenths_secs)?,
+        };
+        Some((sequence, settings))
+    }
+
+    /// Reads settings from the payload of a version 1 record: the time control, stage plan and
+    /// flag behavior, followed by the players' settings.
+    fn from_v1_payload(payload: &[u8]) -> Option<Settings> {
+        let mut time = [0; TIME_BYTES];
+        time[..2].copy_from_slice(&payload[..2]);
+        time[2..].copy_from_slice(&payload[3..]);
+        Some(Settings {
+            time: TimeSettings::read(&time)?,
+            flag_behavior: *FLAG_BEHAVIORS.get(payload[2] as usize)?,
+            ..Settings::default()
+        })
+    }
+
+    /// Returns the settings as a record of version 1 (see [`Settings::from_v1_payload`]),
+    /// as saved by earlier builds.
+    #[cfg(test)]
+    pub(crate) fn to_v1_record(&self, sequence: u32) -> [u8; V1_RECORD_BYTES] {
+        let mut record = [0xFF; V1_RECORD_BYTES];
+        let payload_bytes = VERSION_PAYLOAD_BYTES[0];
+        record[..2].copy_from_slice(&MAGIC);
+        record[2] = 1;
+        record[3] = payload_bytes as u8;
+        record[4..HEADER_BYTES].copy_from_slice(&sequence.to_le_bytes());
+        let mut time = [0; TIME_BYTES];
+        self.time.write(&mut time);
+        let payload = &mut record[HEADER_BYTES..HEADER_BYTES + payload_bytes];
+        payload[..2].copy_from_slice(&time[..2]);
+        payload[2] = FLAG_BEHAVIORS
+            .iter()
+            .position(|flag_behavior| *flag_behavior == self.flag_behavior)
+            .unwrap_or(0) as u8;
+        payload[3..].copy_from_slice(&time[2..]);
+        let end = HEADER_BYTES + payload_bytes;
+        let checksum = crc16(&record[..end]);
+        record[end..end + CHECKSUM_BYTES].copy_from_slice(&checksum.to_le_bytes());
+        record
+    }
+}
+
+/// Reads the value stored (as an index into the given values) at the given offset of a payload,
+/// or returns the given default if the payload is too old to hold it.
+/// Returns None if the index is out of range.
+fn read_index<T: Copy>(payload: &[u8], offset: usize, values: &[T], default: T) -> Option<T> {
+    match payload.get(offset) {
+        Some(index) => values.get(*index as usize).copied(),
+        None => Some(default),
+    }
+}
+
+impl Default for Settings {
+    /// Settings of a new game (10 minutes each, no increment, stopping at 00:00, players unlinked),
+    /// with sound and backlight on, warning below 30 seconds and tenths shown below 10 seconds.
+    fn default() -> Self {
+        crate::Game::new().settings()
+    }
+}
+
+impl TimeSettings {
+    /// Write the time settings to the given bytes (of TIME_BYTES).
+    fn write(&self, bytes: &mut [u8]) {
+        bytes[0] = TIME_CONTROLS
+            .iter()
+            .position(|time_control| *time_control == self.time_control)
+            .unwrap_or(0) as u8;
+        bytes[1] = self.stage_plan;
+        for (bytes, player) in bytes[2..].chunks_exact_mut(PLAYER_BYTES).zip(&self.players) {
+            bytes[..4].copy_from_slice(&player.millis.to_le_bytes());
+            bytes[4..8].copy_from_slice(&player.bonus_millis.to_le_bytes());
+            bytes[8] = player.periods;
+            bytes[9] = player.block_moves;
+        }
+    }
+
+    /// Read time settings from the given bytes (of TIME_BYTES), if they are in range.
+    fn read(bytes: &[u8]) -> Option<TimeSettings> {
+        let mut players = [PlayerSettings::default(); 2];
+        for (player, bytes) in players
+            .iter_mut()
+            .zip(bytes[2..].chunks_exact(PLAYER_BYTES))
+        {
+            *player = PlayerSettings {
+                millis: i32::from_le_bytes(bytes[..4].try_into().ok()?),
+                bonus_millis: i32::from_le_bytes(bytes[4..8].try_into().ok()?),
+                periods: bytes[8],
+                block_moves: bytes[9],
+            };
+            if player.millis <= 0
+                || player.bonus_millis < 0
+                || player.periods == 0
+                || player.block_moves == 0
+            {
+                return None;
+            }
+        }
+        if bytes[1] as usize >= STAGE_PLANS.len() {
+            return None;
+        }
+        Some(TimeSettings {
+            time_control: *TIME_CONTROLS.get(bytes[0] as usize)?,
+            stage_plan: bytes[1],
+            players,
+        })
+    }
+}
+
+impl Default for PlayerSettings {
+    fn default() -> Self {
+        Settings::default().time.players[0]
+    }
+}
+
+/// CRC-16/CCITT-FALSE of the given bytes.
+fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc = 0xFFFF_u16;
+    for byte in bytes {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets::PRESETS;
+
+    fn settings() -> Settings {
+        let mut settings = Settings {
+            flag_behavior: FlagBehavior::CountNegative,
+            link: Link::Odds(5, 1),
+            sound: false,
+            warning_secs: 10,
+            tenths_secs: 20,
+            ..Settings::default()
+        };
+        settings.time.time_control = TimeControl::Canadian;
+        settings.time.stage_plan = 2;
+        settings.time.players[0].millis = 5 * 60_000 + 999;
+        settings.time.players[1].bonus_millis = 3_000;
+        settings.time.players[1].block_moves = 25;
+        settings.custom_presets.push(PRESETS[6].time).unwrap();
+        settings.custom_presets.push(PRESETS[1].time).unwrap();
+        settings
+    }
+
+    /// Re-stamp the record's checksum after modifying it.
+    fn fix_checksum(record: &mut [u8; RECORD_BYTES]) {
+        let end = HEADER_BYTES + PAYLOAD_BYTES;
+        let checksum = crc16(&record[..end]);
+        record[end..end + CHECKSUM_BYTES].copy_from_slice(&checksum.to_le_bytes());
+    }
+
+    #[test]
+    fn crc16_matches_reference_value() {
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let record = settings().to_record(42);
+        assert_eq!(Settings::from_record(&record), Some((42, settings())));
+        assert!(record[HEADER_BYTES + PAYLOAD_BYTES + CHECKSUM_BYTES..]
+            .iter()
+            .all(|byte| *byte == 0xFF));
+        let full = Settings {
+            custom_presets: Vec::from_slice(&[PRESETS[0].time; MAX_CUSTOM_PRESETS]).unwrap(),
+            ..settings()
+        };
+        assert_eq!(Settings::from_record(&full.to_record(1)), Some((1, full)));
+    }
+
+    #[test]
+    fn erased_record_is_rejected() {
+        assert_eq!(Settings::from_record(&[0xFF; RECORD_BYTES]), None);
+    }
+
+    #[test]
+    fn any_corrupted_byte_is_rejected() {
+        let record = settings().to_record(7);
+        for i in 0..HEADER_BYTES + PAYLOAD_BYTES + CHECKSUM_BYTES {
+            let mut corrupted = record;
+            corrupted[i] ^= 0x04;
+            assert_eq!(Settings::from_record(&corrupted), None, "byte {i}");
+        }
+    }
+
+    /// Returns the given record as saved by the given (earlier) version: without the settings
+    /// added since.
+    fn older_record(record: &[u8; RECORD_BYTES], version: u8) -> [u8; RECORD_BYTES] {
+        let payload_bytes = VERSION_PAYLOAD_BYTES[version as usize - 1];
+        let end = HEADER_BYTES + payload_bytes;
+        let mut older = [0xFF; RECORD_BYTES];
+        older[..end].copy_from_slice(&record[..end]);
+        older[2] = version;
+        older[3] = payload_bytes as u8;
+        let checksum = crc16(&older[..end]);
+        older[end..end + CHECKSUM_BYTES].copy_from_slice(&checksum.to_le_bytes());
+        older
+    }
+
+    #[test]
+    fn older_versions_are_upgraded() {
+        let record = settings().to_record(7);
+        let defaults = Settings::default();
+        let upgraded = Settings::from_record(&older_record(&record, 5));
+        let expected = Settings {
+            tenths_secs: defaults.tenths_secs,
+            ..settings()
+        };
+        assert_eq!(upgraded, Some((7, expected)));
+        let upgraded = Settings::from_record(&older_record(&record, 4));
+        let expected = Settings {
+            warning_secs: defaults.warning_secs,
+            tenths_secs: defaults.tenths_secs,
+            ..settings()
+        };
+        assert_eq!(upgraded, Some((7, expected)));
+        let upgraded = Settings::from_record(&older_record(&record, 2));
+        let expected = Settings {
+            sound: true,
+            backlight: true,
+            link: defaults.link,
+            warning_secs: defaults.warning_secs,
+            tenths_secs: defaults.tenths_secs,
+            ..settings()
+        };
+        assert_eq!(upgraded, Some((7, expected)));
+    }
+
+    #[test]
+    fn version_1_records_are_upgraded() {
+        let record = settings().to_v1_record(3);
+        let expected = Settings {
+            time: settings().time,
+            flag_behavior: FlagBehavior::CountNegative,
+            ..Settings::default()
+        };
+        assert_eq!(Settings::from_record(&record), Some((3, expected)));
+        let mut corrupted = record;
+        corrupted[HEADER_BYTES + 2] = FLAG_BEHAVIORS.len() as u8;
+        assert_eq!(Settings::from_record(&corrupted), None);
+    }
+
+    #[test]
+    fn unknown_versions_are_rejected() {
+        for version in [0, VERSION + 1] {
+            let mut record = settings().to_record(7);
+            record[2] = version;
+            fix_checksum(&mut record);
+            assert_eq!(Settings::from_record(&record), None);
+        }
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        let mut invalid = settings();
+        invalid.time.stage_plan = STAGE_PLANS.len() as u8;
+        assert_eq!(Settings::from_record(&invalid.to_record(1)), None);
+        let mut invalid = settings();
+        invalid.custom_presets[1].players[1].millis = 0;
+        assert_eq!(Settings::from_record(&invalid.to_record(1)), None);
+        let mut record = settings().to_record(1);
+        record[HEADER_BYTES + TIME_BYTES + 1] = MAX_CUSTOM_PRESETS as u8 + 1;
+        fix_checksum(&mut record);
+        assert_eq!(Settings::from_record(&record), None);
+        let mut record = settings().to_record(1);
+        record[HEADER_BYTES + OPTIONS_OFFSET] |= 0x80;
+        fix_checksum(&mut record);
+        assert_eq!(Settings::from_record(&record), None);
+        let mut record = settings().to_record(1);
+        record[HEADER_BYTES + LINK_OFFSET] = LINKS.len() as u8;
+        fix_checksum(&mut record);
+        assert_eq!(Settings::from_record(&record), None);
+        let mut record = settings().to_record(1);
+        record[HEADER_BYTES + WARNING_OFFSET] = WARNING_SECS.len() as u8;
+        fix_checksum(&mut record);
+        assert_eq!(Settings::from_record(&record), None);
+        let mut record = settings().to_record(1);
+        record[HEADER_BYTES + TENTHS_OFFSET] = TENTHS_SECS.len() as u8;
+        fix_checksum(&mut record);
+        assert_eq!(Settings::from_record(&record), None);
+    }
+}
@@ -0,0 +1,125 @@
+/// Sound made by the clock's buzzer (if it has one), in response to the game (see
+/// [`crate::Action::Sound`]).
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Sound {
+    /// Short click as a player ends their turn.
+    Click,
+    /// Double beep as a player's time drops below the low-time warning threshold.
+    Warning,
+    /// Long tone as a player's flag falls.
+    Flag,
+    /// Rising beeps as a value is confirmed in the settings menu.
+    Confirm,
+}
+
+/// A tone of the given frequency (in Hz; 0 for silence), lasting for the given time.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Note {
+    pub hz: u16,
+    pub millis: u16,
+}
+
+const CLICK: &[Note] = &[note(4000, 15)];
+const WARNING: &[Note] = &[note(2000, 120), note(0, 80), note(2000, 120)];
+const FLAG: &[Note] = &[note(1000, 1500)];
+const CONFIRM: &[Note] = &[note(2500, 60), note(0, 30), note(3300, 90)];
+
+const fn note(hz: u16, millis: u16) -> Note {
+    Note { hz, millis }
+}
+
+impl Sound {
+    /// Returns the notes of the sound, in the order they are played.
+    pub fn notes(self) -> &'static [Note] {
+        match self {
+            Sound::Click => CLICK,
+            Sound::Warning => WARNING,
+            Sound::Flag => FLAG,
+            Sound::Confirm => CONFIRM,
+        }
+    }
+}
+
+/// Plays a sound note by note, telling the caller when to change the buzzer's tone so that it
+/// can carry on with other work in between (e.g. awaiting the next note with a timer).
+pub struct Melody {
+    notes: &'static [Note],
+    next_note: Option<u64>,
+}
+
+impl Default for Melody {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Melody {
+    /// Returns a melody with nothing to play.
+    pub const fn new() -> Melody {
+        Melody {
+            notes: &[],
+            next_note: None,
+        }
+    }
+
+    /// Start playing the given sound, cutting short any sound already playing.
+    /// Returns the frequency of its first note.
+    pub fn play(&mut self, sound: Sound, now: u64) -> u16 {
+        self.notes = sound.notes();
+        self.next_note = Some(now);
+        self.poll(now).unwrap_or(0)
+    }
+
+    /// Returns the time at which the current note ends, if a sound is playing.
+    pub fn next_note(&self) -> Option<u64> {
+        self.next_note
+    }
+
+    /// Returns the frequency to switch to once the current note has ended (see
+    /// [`Melody::next_note`]): that of the next note, or 0 (silence) at the end of the sound.
+    pub fn poll(&mut self, now: u64) -> Option<u16> {
+        match self.next_note {
+            Some(next_note) if now >= next_note => match self.notes.split_first() {
+                Some((note, notes)) => {
+                    self.notes = notes;
+                    self.next_note = Some(next_note + note.millis as u64);
+                    Some(note.hz)
+                }
+                None => {
+                    self.next_note = None;
+                    Some(0)
+                }
+            },
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notes_play_in_turn_then_fall_silent() {
+        let mut melody = Melody::new();
+        assert_eq!(melody.next_note(), None);
+        assert_eq!(melody.play(Sound::Warning, 1_000), 2000);
+        assert_eq!(melody.next_note(), Some(1_120));
+        assert_eq!(melody.poll(1_119), None);
+        assert_eq!(melody.poll(1_120), Some(0));
+        assert_eq!(melody.poll(1_200), Some(2000));
+        assert_eq!(melody.poll(1_320), Some(0));
+        assert_eq!(melody.next_note(), None);
+        assert_eq!(melody.poll(5_000), None);
+    }
+
+    #[test]
+    fn new_sound_cuts_short_the_last() {
+        let mut melody = Melody::new();
+        melody.play(Sound::Flag, 0);
+        assert_eq!(melody.play(Sound::Click, 100), 4000);
+        assert_eq!(melody.poll(115), Some(0));
+        assert_eq!(melody.next_note(), None);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::settings::{Settings, RECORD_BYTES, V1_RECORD_BYTES};
+
+/// Size of a flash sector, the smallest region which can be erased (4 KiB on the RP2040).
+pub const SECTOR_BYTES: u32 = 4096;
+/// Size of the flash region used to store settings: two sectors, written alternately.
+pub const STORAGE_BYTES: u32 = 2 * SECTOR_BYTES;
+const SLOTS_PER_SECTOR: u32 = SECTOR_BYTES / RECORD_BYTES as u32;
+const SLOTS: u32 = STORAGE_BYTES / RECORD_BYTES as u32;
+
+/// Low-level operations of (NOR) flash memory, as used by [`SettingsStore`].
+/// Offsets are relative to the start of the region reserved for settings.
+pub trait Flash {
+    type Error;
+
+    /// Read the bytes at the given offset.
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Write the given bytes at the given offset, which must have been erased.
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error>;
+
+    /// Erase (i.e. set to 0xFF) the sector starting at the given offset.
+    fn erase_sector(&mut self, offset: u32) -> Result<(), Self::Error>;
+}
+
+/// Settings persisted to flash, as a log of records (see [`Settings::to_record`]).
+///
+/// Each save is written to the next free slot rather than overwriting the previous record,
+/// spreading wear across the region. A sector is only erased when the log moves on to it, while
+/// the other sector still holds the latest record, so a save interrupted by power loss falls back
+/// to the previous settings (and a corrupted region to none, i.e. the defaults).
+pub struct SettingsStore<F: Flash> {
+    flash: F,
+    /// Slot and sequence number of the latest valid record, if any.
+    latest: Option<(u32, u32)>,
+}
+
+impl<F: Flash> SettingsStore<F> {
+    /// Wraps the given flash region (of [`STORAGE_BYTES`]).
+    /// Call [`SettingsStore::load`] before saving, so the log is continued from its latest record.
+    pub fn new(flash: F) -> SettingsStore<F> {
+        SettingsStore {
+            flash,
+            latest: None,
+        }
+    }
+
+    /// Returns the most recently saved settings, or None if no valid record is found.
+    pub fn load(&mut self) -> Result<Option<Settings>, F::Error> {
+        let mut latest = None;
+        self.latest = None;
+        for slot in 0..SLOTS {
+            let mut record = [0; RECORD_BYTES];
+            self.flash.read(slot * RECORD_BYTES as u32, &mut record)?;
+            // records of version 1 were half the size, so either half of a slot may hold one
+            for offset in [0, V1_RECORD_BYTES] {
+                if let Some((sequence, settings)) = Settings::from_record(&record[offset..]) {
+                    if self.latest.is_none_or(|(_, latest)| sequence > latest) {
+                        self.latest = Some((slot, sequence));
+                        latest = Some(settings);
+                    }
+                }
+            }
+        }
+        Ok(latest)
+    }
+
+    /// Save the given settings to the slot after the latest record (see [`SettingsStore::load`]).
+    /// Moves on to the next sector (erasing it first) at the end of a sector, or if the next
+    /// slot isn't blank (e.g. after a save was interrupted).
+    pub fn save(&mut self, settings: &Settings) -> Result<(), F::Error> {
+        let (mut slot, sequence) = match self.latest {
+            Some((slot, sequence)) => ((slot + 1) % SLOTS, sequence.wrapping_add(1)),
+            None => (0, 0),
+        };
+        if slot % SLOTS_PER_SECTOR != 0 && !self.is_erased(slot)? {
+            slot = (slot + SLOTS_PER_SECTOR - slot % SLOTS_PER_SECTOR) % SLOTS;
+        }
+        if slot % SLOTS_PER_SECTOR == 0 {
+            self.flash
+                .erase_sector(slot / SLOTS_PER_SECTOR * SECTOR_BYTES)?;
+        }
+        self.flash
+            .write(slot * RECORD_BYTES as u32, &settings.to_record(sequence))?;
+        self.latest = Some((slot, sequence));
+        Ok(())
+    }
+
+    fn is_erased(&mut self, slot: u32) -> Result<bool, F::Error> {
+        let mut record = [0; RECORD_BYTES];
+        self.flash.read(slot * RECORD_BYTES as u32, &mut record)?;
+        Ok(record.iter().all(|byte| *byte == 0xFF))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time_control::TimeControl;
+
+    /// Flash in memory, which (like NOR flash) can only clear bits when written.
+    struct MemoryFlash {
+        bytes: Vec<u8>,
+        erases: [u32; 2],
+    }
+
+    impl MemoryFlash {
+        fn new() -> MemoryFlash {
+            MemoryFlash {
+                bytes: vec![0xFF; STORAGE_BYTES as usize],
+                erases: [0; 2],
+            }
+        }
+    }
+
+    impl Flash for MemoryFlash {
+        type Error = core::convert::Infallible;
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            let offset = offset as usize;
+            bytes.copy_from_slice(&self.bytes[offset..offset + bytes.len()]);
+            Ok(())
+        }
+
+        fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+            for (cell, byte) in self.bytes[offset as usize..].iter_mut().zip(bytes) {
+                *cell &= byte;
+            }
+            Ok(())
+        }
+
+        fn erase_sector(&mut self, offset: u32) -> Result<(), Self::Error> {
+            assert_eq!(offset % SECTOR_BYTES, 0);
+            let start = offset as usize;
+            self.bytes[start..start + SECTOR_BYTES as usize].fill(0xFF);
+            self.erases[(offset / SECTOR_BYTES) as usize] += 1;
+            Ok(())
+        }
+    }
+
+    fn settings(minutes: i32) -> Settings {
+        let mut settings = Settings::default();
+        settings.time.players[0].millis = minutes * 60_000;
+        settings
+    }
+
+    #[test]
+    fn empty_flash_has_no_settings() {
+        let mut store = SettingsStore::new(MemoryFlash::new());
+        assert_eq!(store.load().unwrap(), None);
+    }
+
+    #[test]
+    fn latest_save_is_loaded_after_restart() {
+        let mut store = SettingsStore::new(MemoryFlash::new());
+        store.load().unwrap();
+        store.save(&settings(3)).unwrap();
+        store.save(&settings(5)).unwrap();
+        let mut store = SettingsStore::new(store.flash);
+        assert_eq!(store.load().unwrap(), Some(settings(5)));
+        store.save(&settings(7)).unwrap();
+        let mut store = SettingsStore::new(store.flash);
+        assert_eq!(store.load().unwrap(), Some(settings(7)));
+    }
+
+    #[test]
+    fn saves_are_spread_across_both_sectors() {
+        let mut store = SettingsStore::new(MemoryFlash::new());
+        let saves = 5 * SLOTS as i32 + 3;
+        for minutes in 1..=saves {
+            store.save(&settings(minutes)).unwrap();
+        }
+        // each sector is erased once per pass through the log
+        assert_eq!(store.flash.erases, [6, 5]);
+        let mut store = SettingsStore::new(store.flash);
+        assert_eq!(store.load().unwrap(), Some(settings(saves)));
+    }
+
+    #[test]
+    fn settings_saved_by_version_1_are_loaded() {
+        let mut flash = MemoryFlash::new();
+        for (sequence, minutes) in [(4, 3), (5, 5), (6, 7)] {
+            let offset = (sequence - 4) * V1_RECORD_BYTES as u32;
+            flash
+                .write(offset, &settings(minutes).to_v1_record(sequence))
+                .unwrap();
+        }
+        let mut store = SettingsStore::new(flash);
+        assert_eq!(store.load().unwrap(), Some(settings(7)));
+        // the log carries on after them
+        store.save(&settings(9)).unwrap();
+        let mut store = SettingsStore::new(store.flash);
+        assert_eq!(store.load().unwrap(), Some(settings(9)));
+        assert_eq!(store.latest, Some((2, 7)));
+    }
+
+    #[test]
+    fn corrupted_latest_record_falls_back_to_previous() {
+        let mut store = SettingsStore::new(MemoryFlash::new());
+        store.save(&settings(3)).unwrap();
+        store.save(&settings(5)).unwrap();
+        store.flash.bytes[RECORD_BYTES + 9] ^= 0x01;
+        let mut store = SettingsStore::new(store.flash);
+        assert_eq!(store.load().unwrap(), Some(settings(3)));
+        // saving skips to the next sector, keeping the previous record until the save completes
+        let mut expected = settings(9);
+        expected.time.time_control = TimeControl::Delay;
+        store.save(&expected).unwrap();
+        assert_eq!(store.flash.erases, [1, 1]);
+        let mut store = SettingsStore::new(store.flash);
+        assert_eq!(store.load().unwrap(), Some(expected));
+    }
+}
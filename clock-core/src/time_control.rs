@@ -0,0 +1,613 @@
+use core::fmt::Write;
+use heapless::String;
+
+use crate::MINS_TO_MILLIS;
+
+/// Rule used to adjust a player's clock around each of their moves.
+/// The amount of time involved (the player's "bonus") is configured separately.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TimeControl {
+    /// Fischer increment: bonus is added to the player's time after every move.
+    Increment,
+    /// Simple (US) delay: clock only starts counting down once the bonus has elapsed.
+    Delay,
+    /// Bronstein delay: after every move, the lesser of the time used and the bonus is added back.
+    Bronstein,
+    /// Hourglass: time used by the player is added to their opponent's clock (bonus is unused).
+    Hourglass,
+    /// Japanese byo-yomi: once main time runs out, the player gets a number of overtime periods
+    /// of bonus length. A period is used up only when overrun, and resets after every move.
+    ByoYomi,
+    /// Canadian overtime: once main time runs out, the player must make a block of moves within
+    /// the bonus time. The block (and its time) resets once those moves are made.
+    Canadian,
+}
+
+/// Overtime state of a player's clock (used by byo-yomi and Canadian overtime).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Overtime {
+    /// Byo-yomi periods remaining, including the current one once in overtime.
+    pub periods: u8,
+    /// Moves to be made within each Canadian overtime block.
+    pub block_moves: u8,
+    /// Moves remaining in the current Canadian overtime block.
+    pub moves_left: u8,
+    /// Set once the player's main time has run out.
+    pub active: bool,
+}
+
+impl TimeControl {
+    /// Returns the next time control, used to cycle through options during the "pre-game" phase.
+    pub fn next(self) -> TimeControl {
+        match self {
+            TimeControl::Increment => TimeControl::Delay,
+            TimeControl::Delay => TimeControl::Bronstein,
+            TimeControl::Bronstein => TimeControl::Hourglass,
+            TimeControl::Hourglass => TimeControl::ByoYomi,
+            TimeControl::ByoYomi => TimeControl::Canadian,
+            TimeControl::Canadian => TimeControl::Increment,
+        }
+    }
+
+    /// Short name of the time control, as shown on the LCD (max 8 chars).
+    pub fn label(self) -> &'static str {
+        match self {
+            TimeControl::Increment => "Incr",
+            TimeControl::Delay => "Delay",
+            TimeControl::Bronstein => "Bronst.",
+            TimeControl::Hourglass => "Hourgl.",
+            TimeControl::ByoYomi => "Byo-yomi",
+            TimeControl::Canadian => "Canadian",
+        }
+    }
+
+    /// Returns true if the time control has an overtime phase once main time runs out.
+    pub fn has_overtime(self) -> bool {
+        matches!(self, TimeControl::ByoYomi | TimeControl::Canadian)
+    }
+
+    /// Time charged against the player's clock after `elapsed` millis of their turn.
+    pub fn charged_millis(self, elapsed: i32, bonus: i32) -> i32 {
+        match self {
+            TimeControl::Increment
+            | TimeControl::Bronstein
+            | TimeControl::Hourglass
+            | TimeControl::ByoYomi
+            | TimeControl::Canadian => elapsed,
+            TimeControl::Delay => (elapsed - bonus).max(0),
+        }
+    }
+
+    /// Time credited back to the player's clock on completing a move that took `elapsed` millis.
+    pub fn credited_millis(self, elapsed: i32, bonus: i32) -> i32 {
+        match self {
+            TimeControl::Increment => bonus,
+            TimeControl::Delay
+            | TimeControl::Hourglass
+            | TimeControl::ByoYomi
+            | TimeControl::Canadian => 0,
+            TimeControl::Bronstein => elapsed.min(bonus),
+        }
+    }
+
+    /// Enters overtime (byo-yomi periods or a Canadian block, of bonus length)
+    /// while the player's time (`millis`) has run out.
+    /// Returns the updated time remaining and overtime state.
+    pub fn after_flag(
+        self,
+        mut millis: i32,
+        mut overtime: Overtime,
+        bonus: i32,
+    ) -> (i32, Overtime) {
+        if bonus <= 0 {
+            return (millis, overtime);
+        }
+        match self {
+            TimeControl::ByoYomi => {
+                while millis <= 0 && overtime.periods > 0 {
+                    if overtime.active {
+                        overtime.periods -= 1;
+                        if overtime.periods == 0 {
+                            break;
+                        }
+                    } else {
+                        overtime.active = true;
+                    }
+                    millis += bonus;
+                }
+            }
+            TimeControl::Canadian if millis <= 0 && !overtime.active => {
+                overtime.active = true;
+                overtime.moves_left = overtime.block_moves;
+                millis += bonus;
+            }
+            _ => (),
+        }
+        (millis, overtime)
+    }
+
+    /// Updates overtime after a move: resets the current byo-yomi period, or counts the move
+    /// towards the current Canadian block (resetting it once complete).
+    /// Returns the updated time remaining and overtime state.
+    pub fn after_move(self, millis: i32, mut overtime: Overtime, bonus: i32) -> (i32, Overtime) {
+        if !overtime.active || millis <= 0 {
+            return (millis, overtime);
+        }
+        match self {
+            TimeControl::ByoYomi => (bonus, overtime),
+            TimeControl::Canadian => {
+                overtime.moves_left = overtime.moves_left.saturating_sub(1);
+                if overtime.moves_left == 0 {
+                    overtime.moves_left = overtime.block_moves;
+                    (bonus, overtime)
+                } else {
+                    (millis, overtime)
+                }
+            }
+            _ => (millis, overtime),
+        }
+    }
+}
+
+/// What happens once a player's time runs out.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FlagBehavior {
+    /// Clock stops at 00:00 and the game ends (tournament play).
+    HardStop,
+    /// Clock keeps counting into negative time and the game continues (casual play).
+    CountNegative,
+}
+
+impl FlagBehavior {
+    /// Returns the next flag behavior, used to cycle through options during the "pre-game" phase.
+    pub fn next(self) -> FlagBehavior {
+        match self {
+            FlagBehavior::HardStop => FlagBehavior::CountNegative,
+            FlagBehavior::CountNegative => FlagBehavior::HardStop,
+        }
+    }
+
+    /// Description of the flag behavior, as shown on the LCD (max 16 chars).
+    pub fn label(self) -> &'static str {
+        match self {
+            FlagBehavior::HardStop => "Stop at 00:00",
+            FlagBehavior::CountNegative => "Count negative",
+        }
+    }
+
+    /// Returns true if the game ends once a player's time runs out.
+    pub fn ends_game(self) -> bool {
+        self == FlagBehavior::HardStop
+    }
+
+    /// Time remaining as shown to the players, given the actual time remaining (`millis`).
+    pub fn displayed_millis(self, millis: i32) -> i32 {
+        match self {
+            FlagBehavior::HardStop => millis.max(0),
+            FlagBehavior::CountNegative => millis,
+        }
+    }
+}
+
+/// How adjusting one player's time, bonus or overtime during the pre-game phase affects their
+/// opponent's.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Link {
+    /// Each player's settings are adjusted separately.
+    Unlinked,
+    /// Adjusting either player's settings sets both players' alike.
+    Linked,
+    /// Time odds (e.g. for a coach giving a junior 5 minutes to their 1): players' times are kept
+    /// in the given ratio (red, blue), while their bonuses and overtime are adjusted separately.
+    Odds(u8, u8),
+}
+
+/// Links between players, in the order they are selected during the "pre-game" phase.
+pub const LINKS: [Link; 10] = [
+    Link::Unlinked,
+    Link::Linked,
+    Link::Odds(2, 1),
+    Link::Odds(3, 1),
+    Link::Odds(5, 1),
+    Link::Odds(10, 1),
+    Link::Odds(1, 2),
+    Link::Odds(1, 3),
+    Link::Odds(1, 5),
+    Link::Odds(1, 10),
+];
+
+impl Link {
+    /// Returns the next link, used to cycle through options during the "pre-game" phase.
+    pub fn next(self) -> Link {
+        let index = LINKS.iter().position(|link| *link == self).unwrap_or(0);
+        LINKS[(index + 1) % LINKS.len()]
+    }
+
+    /// Description of the link, as shown on the LCD (max 16 chars), e.g. "Odds 5:1".
+    pub fn label(self) -> String<16> {
+        let mut label: String<16> = String::new();
+        match self {
+            Link::Unlinked => label.push_str("Unlinked").unwrap(),
+            Link::Linked => label.push_str("Linked").unwrap(),
+            Link::Odds(red, blue) => core::write!(&mut label, "Odds {}:{}", red, blue).unwrap(),
+        }
+        label
+    }
+}
+
+/// A period of play within a staged time control (e.g. "40 moves in 90 minutes").
+#[derive(Clone, Copy)]
+pub struct Stage {
+    /// Time allotted for the stage.
+    pub millis: i32,
+    /// Moves to be completed within the stage (`None` if it lasts for the rest of the game).
+    pub moves: Option<u16>,
+}
+
+/// Rule deciding when the next stage's time is added to a player's clock.
+#[derive(Clone, Copy, PartialEq)]
+pub enum StageRule {
+    /// Added as soon as the player completes the stage's moves.
+    Moves,
+    /// Added only once the stage's time runs out (move count is checked by the arbiter).
+    Flag,
+}
+
+/// A staged (tournament) time control. The player's bonus applies from move 1 in every stage.
+pub struct StagePlan {
+    /// Name of the plan, as shown on the LCD (max 16 chars).
+    pub label: &'static str,
+    /// Stages in order of play; empty for a single stage using the player's selected time.
+    pub stages: &'static [Stage],
+    /// When the next stage's time is added.
+    pub rule: StageRule,
+}
+
+/// Staged time controls available during the "pre-game" phase.
+pub const STAGE_PLANS: [StagePlan; 4] = [
+    StagePlan {
+        label: "Single stage",
+        stages: &[],
+        rule: StageRule::Moves,
+    },
+    StagePlan {
+        label: "40/90 SD/30",
+        stages: &[
+            Stage {
+                millis: 90 * MINS_TO_MILLIS,
+                moves: Some(40),
+            },
+            Stage {
+                millis: 30 * MINS_TO_MILLIS,
+                moves: None,
+            },
+        ],
+        rule: StageRule::Moves,
+    },
+    StagePlan {
+        label: "40/90 SD/30 flag",
+        stages: &[
+            Stage {
+                millis: 90 * MINS_TO_MILLIS,
+                moves: Some(40),
+            },
+            Stage {
+                millis: 30 * MINS_TO_MILLIS,
+                moves: None,
+            },
+        ],
+        rule: StageRule::Flag,
+    },
+    StagePlan {
+        label: "40/120 SD/30",
+        stages: &[
+            Stage {
+                millis: 120 * MINS_TO_MILLIS,
+                moves: Some(40),
+            },
+            Stage {
+                millis: 30 * MINS_TO_MILLIS,
+                moves: None,
+            },
+        ],
+        rule: StageRule::Moves,
+    },
+];
+
+impl StagePlan {
+    /// Returns true if the plan has more than one stage.
+    pub fn is_staged(&self) -> bool {
+        self.stages.len() > 1
+    }
+
+    /// Total number of moves to be completed by the end of the given stage
+    /// (`None` if the stage lasts for the rest of the game).
+    fn moves_by_end_of(&self, stage: usize) -> Option<u16> {
+        let mut total = 0;
+        for s in self.stages.iter().take(stage + 1) {
+            total += s.moves?;
+        }
+        Some(total)
+    }
+
+    /// Enters any stages due after completing `moves` moves in total.
+    /// Returns the updated time remaining and current stage.
+    pub fn after_move(&self, mut millis: i32, mut stage: usize, moves: u16) -> (i32, usize) {
+        if self.rule == StageRule::Moves {
+            while stage + 1 < self.stages.len()
+                && self.moves_by_end_of(stage).is_some_and(|n| moves >= n)
+            {
+                stage += 1;
+                millis += self.stages[stage].millis;
+            }
+        }
+        (millis, stage)
+    }
+
+    /// Enters any stages due because the player's time (`millis`) has run out.
+    /// Returns the updated time remaining and current stage.
+    pub fn after_flag(&self, mut millis: i32, mut stage: usize) -> (i32, usize) {
+        if self.rule == StageRule::Flag {
+            while millis <= 0 && stage + 1 < self.stages.len() {
+                stage += 1;
+                millis += self.stages[stage].millis;
+            }
+        }
+        (millis, stage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OVERTIME: Overtime = Overtime {
+        periods: 3,
+        block_moves: 10,
+        moves_left: 0,
+        active: false,
+    };
+
+    /// Time remaining after a move taking `elapsed` millis, starting from `millis_left`.
+    fn after_move(time_control: TimeControl, millis_left: i32, elapsed: i32, bonus: i32) -> i32 {
+        millis_left - time_control.charged_millis(elapsed, bonus)
+            + time_control.credited_millis(elapsed, bonus)
+    }
+
+    #[test]
+    fn increment_adds_bonus_after_move() {
+        assert_eq!(
+            after_move(TimeControl::Increment, 60_000, 4_000, 2_000),
+            58_000
+        );
+        assert_eq!(
+            after_move(TimeControl::Increment, 60_000, 1_000, 2_000),
+            61_000
+        );
+    }
+
+    #[test]
+    fn delay_charges_only_time_beyond_bonus() {
+        assert_eq!(TimeControl::Delay.charged_millis(3_000, 5_000), 0);
+        assert_eq!(TimeControl::Delay.charged_millis(8_000, 5_000), 3_000);
+        assert_eq!(after_move(TimeControl::Delay, 60_000, 3_000, 5_000), 60_000);
+        assert_eq!(after_move(TimeControl::Delay, 60_000, 8_000, 5_000), 57_000);
+    }
+
+    #[test]
+    fn bronstein_charges_full_time_during_turn() {
+        assert_eq!(TimeControl::Bronstein.charged_millis(3_000, 5_000), 3_000);
+        assert_eq!(TimeControl::Bronstein.charged_millis(8_000, 5_000), 8_000);
+    }
+
+    #[test]
+    fn bronstein_adds_back_lesser_of_time_used_and_bonus() {
+        assert_eq!(TimeControl::Bronstein.credited_millis(3_000, 5_000), 3_000);
+        assert_eq!(TimeControl::Bronstein.credited_millis(8_000, 5_000), 5_000);
+        assert_eq!(
+            after_move(TimeControl::Bronstein, 60_000, 3_000, 5_000),
+            60_000
+        );
+        assert_eq!(
+            after_move(TimeControl::Bronstein, 60_000, 8_000, 5_000),
+            57_000
+        );
+    }
+
+    #[test]
+    fn bronstein_never_exceeds_starting_time() {
+        let start = 60_000;
+        let mut millis_left = start;
+        for elapsed in [0, 500, 5_000, 100, 12_000, 1] {
+            millis_left = after_move(TimeControl::Bronstein, millis_left, elapsed, 5_000);
+            assert!(millis_left <= start);
+        }
+        assert_eq!(millis_left, start - 7_000);
+    }
+
+    #[test]
+    fn hourglass_charges_full_time_without_credit() {
+        assert_eq!(TimeControl::Hourglass.charged_millis(3_000, 5_000), 3_000);
+        assert_eq!(TimeControl::Hourglass.credited_millis(3_000, 5_000), 0);
+    }
+
+    #[test]
+    fn next_cycles_through_all_time_controls() {
+        let mut time_control = TimeControl::Increment;
+        for expected in [
+            TimeControl::Delay,
+            TimeControl::Bronstein,
+            TimeControl::Hourglass,
+            TimeControl::ByoYomi,
+            TimeControl::Canadian,
+            TimeControl::Increment,
+        ] {
+            time_control = time_control.next();
+            assert!(time_control == expected);
+        }
+    }
+
+    #[test]
+    fn stage_time_added_on_reaching_move_count() {
+        let plan = &STAGE_PLANS[1];
+        assert_eq!(plan.after_move(1_000, 0, 39), (1_000, 0));
+        assert_eq!(
+            plan.after_move(1_000, 0, 40),
+            (1_000 + 30 * MINS_TO_MILLIS, 1)
+        );
+        // final stage lasts for the rest of the game
+        assert_eq!(plan.after_move(1_000, 1, 80), (1_000, 1));
+        // move rule ignores flag fall
+        assert_eq!(plan.after_flag(-1_000, 0), (-1_000, 0));
+    }
+
+    #[test]
+    fn stage_time_added_on_flag_fall() {
+        let plan = &STAGE_PLANS[2];
+        assert_eq!(plan.after_flag(1_000, 0), (1_000, 0));
+        assert_eq!(plan.after_flag(-1_000, 0), (30 * MINS_TO_MILLIS - 1_000, 1));
+        assert_eq!(plan.after_flag(-1_000, 1), (-1_000, 1));
+        // flag rule ignores move count
+        assert_eq!(plan.after_move(1_000, 0, 40), (1_000, 0));
+    }
+
+    #[test]
+    fn single_stage_plan_never_adds_time() {
+        let plan = &STAGE_PLANS[0];
+        assert!(!plan.is_staged());
+        assert_eq!(plan.after_move(1_000, 0, 40), (1_000, 0));
+        assert_eq!(plan.after_flag(-1_000, 0), (-1_000, 0));
+    }
+
+    #[test]
+    fn byo_yomi_enters_overtime_when_main_time_runs_out() {
+        let overtime = Overtime {
+            periods: 3,
+            active: false,
+            ..OVERTIME
+        };
+        assert_eq!(
+            TimeControl::ByoYomi.after_flag(1_000, overtime, 30_000),
+            (1_000, overtime)
+        );
+        let (millis, overtime) = TimeControl::ByoYomi.after_flag(-1_000, overtime, 30_000);
+        assert_eq!(millis, 29_000);
+        assert_eq!(
+            overtime,
+            Overtime {
+                periods: 3,
+                active: true,
+                ..OVERTIME
+            }
+        );
+    }
+
+    #[test]
+    fn byo_yomi_period_used_up_only_when_overrun() {
+        let overtime = Overtime {
+            periods: 3,
+            active: true,
+            ..OVERTIME
+        };
+        // move made within the period resets it
+        let (millis, overtime) = TimeControl::ByoYomi.after_flag(5_000, overtime, 30_000);
+        assert_eq!(
+            TimeControl::ByoYomi.after_move(millis, overtime, 30_000),
+            (30_000, overtime)
+        );
+        // overrunning two periods
+        let (millis, overtime) = TimeControl::ByoYomi.after_flag(-31_000, overtime, 30_000);
+        assert_eq!(millis, 29_000);
+        assert_eq!(overtime.periods, 1);
+    }
+
+    #[test]
+    fn byo_yomi_flags_after_last_period() {
+        let overtime = Overtime {
+            periods: 1,
+            active: true,
+            ..OVERTIME
+        };
+        let (millis, overtime) = TimeControl::ByoYomi.after_flag(-1_000, overtime, 30_000);
+        assert_eq!(millis, -1_000);
+        assert_eq!(overtime.periods, 0);
+        assert_eq!(
+            TimeControl::ByoYomi.after_move(millis, overtime, 30_000),
+            (-1_000, overtime)
+        );
+    }
+
+    #[test]
+    fn main_time_not_reset_after_move() {
+        let overtime = Overtime {
+            periods: 3,
+            active: false,
+            ..OVERTIME
+        };
+        assert_eq!(
+            TimeControl::ByoYomi.after_move(1_000, overtime, 30_000),
+            (1_000, overtime)
+        );
+    }
+
+    #[test]
+    fn canadian_enters_block_when_main_time_runs_out() {
+        let (millis, overtime) = TimeControl::Canadian.after_flag(-1_000, OVERTIME, 300_000);
+        assert_eq!(millis, 299_000);
+        assert!(overtime.active);
+        assert_eq!(overtime.moves_left, 10);
+        // block time running out is a flag fall
+        assert_eq!(
+            TimeControl::Canadian.after_flag(-1_000, overtime, 300_000),
+            (-1_000, overtime)
+        );
+    }
+
+    #[test]
+    fn canadian_block_resets_once_moves_made() {
+        let overtime = Overtime {
+            moves_left: 2,
+            active: true,
+            ..OVERTIME
+        };
+        let (millis, overtime) = TimeControl::Canadian.after_move(100_000, overtime, 300_000);
+        assert_eq!(millis, 100_000);
+        assert_eq!(overtime.moves_left, 1);
+        let (millis, overtime) = TimeControl::Canadian.after_move(50_000, overtime, 300_000);
+        assert_eq!(millis, 300_000);
+        assert_eq!(overtime.moves_left, 10);
+    }
+
+    #[test]
+    fn canadian_moves_not_counted_in_main_time() {
+        assert_eq!(
+            TimeControl::Canadian.after_move(100_000, OVERTIME, 300_000),
+            (100_000, OVERTIME)
+        );
+    }
+
+    #[test]
+    fn hard_stop_never_displays_negative_time() {
+        assert_eq!(FlagBehavior::HardStop.displayed_millis(1_000), 1_000);
+        assert_eq!(FlagBehavior::HardStop.displayed_millis(-1_000), 0);
+        assert!(FlagBehavior::HardStop.ends_game());
+    }
+
+    #[test]
+    fn count_negative_displays_negative_time() {
+        assert_eq!(FlagBehavior::CountNegative.displayed_millis(-1_000), -1_000);
+        assert!(!FlagBehavior::CountNegative.ends_game());
+    }
+
+    #[test]
+    fn links_cycle_through_odds_and_fit_the_lcd() {
+        let mut link = Link::Unlinked;
+        for expected in LINKS.iter().cycle().skip(1).take(LINKS.len()) {
+            link = link.next();
+            assert_eq!(link, *expected);
+            assert!(link.label().len() <= 16);
+        }
+        assert_eq!(link, Link::Unlinked);
+        assert_eq!(Link::Odds(5, 1).label(), "Odds 5:1");
+    }
+}
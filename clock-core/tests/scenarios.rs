@@ -0,0 +1,205 @@
+//! Replays the scenario files in `tests/scenarios` through the game against a virtual clock.
+//!
+//! A scenario is a list of timestamped steps (in millis), separated by newlines or `;`:
+//! * `t=0 Yellow press` / `t=0 Blue hold`: button event, as sent by the firmware's button watcher
+//! * `t=0 Red down` / `t=1500 Red up`: raw button edges, turned into events by `clock_core::Button`
+//! * `t=500 lcd "Red    #1   Blue" "09:59      10:00"`: assert the LCD rows last drawn
+//! * `t=500 led Blue on`: assert an LED's state (`on` or `off`)
+//! * `t=500 backlight off`: assert the display's backlight state
+//! * `t=500 sound Click`: assert the last sound played since the previous such step (or `none`)
+//! * `t=500 phase Active`: assert the game phase (as `GameStatus` is debug-printed)
+//!
+//! Timer ticks are fed to the game every 100 millis, as in the firmware. Lines starting with `#`
+//! are comments.
+use clock_core::{Action, Button, ButtonEvent, Color, Event, FrameBuffer, Game, Sound};
+
+const TICK_MILLIS: u64 = 100;
+const COLORS: [Color; 3] = [Color::Red, Color::Yellow, Color::Blue];
+
+/// Game running against a virtual clock, with the LEDs and LCD it drives.
+struct Simulation {
+    game: Game,
+    now: u64,
+    next_tick: u64,
+    buttons: [Button; 3],
+    leds: [bool; 3],
+    backlight: bool,
+    sound: Option<Sound>,
+    lcd: FrameBuffer,
+}
+
+impl Simulation {
+    fn new() -> Simulation {
+        let game = Game::new();
+        let mut lcd = FrameBuffer::new();
+        game.render(&mut lcd, 0).unwrap();
+        Simulation {
+            game,
+            now: 0,
+            next_tick: TICK_MILLIS,
+            buttons: COLORS.map(Button::new),
+            leds: [false; 3],
+            backlight: true,
+            sound: None,
+            lcd,
+        }
+    }
+
+    /// Move the clock forward to the given time, feeding in timer ticks and button holds due on the way.
+    fn advance(&mut self, t: u64) {
+        loop {
+            let next_hold = self.buttons.iter().filter_map(Button::next_hold).min();
+            let next = next_hold.map_or(self.next_tick, |hold| hold.min(self.next_tick));
+            if next > t {
+                break;
+            }
+            self.now = next;
+            let holds: Vec<ButtonEvent> = self
+                .buttons
+                .iter_mut()
+                .filter_map(|button| button.poll(next))
+                .collect();
+            for event in holds {
+                self.handle(Event::Button(event));
+            }
+            if self.next_tick == next {
+                self.handle(Event::Tick);
+                self.next_tick += TICK_MILLIS;
+            }
+        }
+        self.now = t;
+    }
+
+    fn handle(&mut self, event: Event) {
+        for action in self.game.handle(event, self.now) {
+            match action {
+                Action::Led(color, on) => self.leds[index(color)] = on,
+                Action::Display => self.game.render(&mut self.lcd, self.now).unwrap(),
+                Action::SaveSettings => (),
+                Action::Backlight(on) => self.backlight = on,
+                Action::Sound(sound) => self.sound = Some(sound),
+            }
+        }
+    }
+
+    /// Run a single scenario step (after the clock has been advanced to its time).
+    fn step(&mut self, step: &str) {
+        let (target, rest) = step.split_once(' ').expect("missing step");
+        let rest = rest.trim();
+        match target {
+            "lcd" => {
+                let rows: Vec<&str> = rest.split('"').skip(1).step_by(2).collect();
+                assert_eq!([self.lcd.row(0), self.lcd.row(1)], rows[..], "lcd");
+            }
+            "led" => {
+                let (color, state) = rest.split_once(' ').expect("missing LED state");
+                assert_eq!(
+                    self.leds[index(color_named(color))],
+                    on_off(state),
+                    "{color} LED"
+                );
+            }
+            "backlight" => assert_eq!(self.backlight, on_off(rest), "backlight"),
+            "sound" => {
+                let sound = self
+                    .sound
+                    .take()
+                    .map_or("none".into(), |sound| format!("{sound:?}"));
+                assert_eq!(sound, rest, "sound");
+            }
+            "phase" => assert_eq!(format!("{:?}", self.game.phase()), rest, "phase"),
+            color => {
+                let color = color_named(color);
+                let event = match rest {
+                    "press" => Some(ButtonEvent::Pressed(color)),
+                    "hold" => Some(ButtonEvent::Held(color)),
+                    "down" => {
+                        self.buttons[index(color)].down(self.now);
+                        None
+                    }
+                    "up" => self.buttons[index(color)].up(),
+                    _ => panic!("unknown button action: {rest}"),
+                };
+                if let Some(event) = event {
+                    self.handle(Event::Button(event));
+                }
+            }
+        }
+    }
+}
+
+fn index(color: Color) -> usize {
+    COLORS.iter().position(|c| *c == color).unwrap()
+}
+
+fn on_off(state: &str) -> bool {
+    match state {
+        "on" => true,
+        "off" => false,
+        _ => panic!("unknown state: {state}"),
+    }
+}
+
+fn color_named(name: &str) -> Color {
+    *COLORS
+        .iter()
+        .find(|color| color.name() == name)
+        .unwrap_or_else(|| panic!("unknown color: {name}"))
+}
+
+/// Replay the given scenario, panicking at the first failed assertion.
+fn run(scenario: &str) {
+    let mut simulation = Simulation::new();
+    let steps = scenario
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .flat_map(|line| line.split(';'))
+        .map(str::trim)
+        .filter(|step| !step.is_empty());
+    for step in steps {
+        let (time, action) = step
+            .strip_prefix("t=")
+            .and_then(|step| step.split_once(' '))
+            .unwrap_or_else(|| panic!("step must start with t=<millis>: {step}"));
+        let t: u64 = time.parse().expect("invalid time");
+        assert!(t >= simulation.now, "steps out of order: {step}");
+        simulation.advance(t);
+        println!("{step}");
+        simulation.step(action);
+    }
+}
+
+#[test]
+fn pre_game_settings() {
+    run(include_str!("scenarios/pre_game_settings.txt"));
+}
+
+#[test]
+fn press_and_hold() {
+    run(include_str!("scenarios/press_and_hold.txt"));
+}
+
+#[test]
+fn turns_and_pause() {
+    run(include_str!("scenarios/turns_and_pause.txt"));
+}
+
+#[test]
+fn presets() {
+    run(include_str!("scenarios/presets.txt"));
+}
+
+#[test]
+fn settings_menu() {
+    run(include_str!("scenarios/settings_menu.txt"));
+}
+
+#[test]
+fn flag_fall() {
+    run(include_str!("scenarios/flag_fall.txt"));
+}
+
+#[test]
+fn time_odds() {
+    run(include_str!("scenarios/time_odds.txt"));
+}
@@ -0,0 +1,7 @@
+MEMORY {
+    BOOT2 : ORIGIN = 0x10000000, LENGTH = 0x100
+    FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100 - 8K
+    /* reserved for persisted settings (see src/storage.rs), kept clear of the program */
+    SETTINGS : ORIGIN = 0x10000000 + 2048K - 8K, LENGTH = 8K
+    RAM   : ORIGIN = 0x20000000, LENGTH = 256K
+}
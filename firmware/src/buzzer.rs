@@ -0,0 +1,36 @@
+//! Piezo buzzer, driven by PWM to play the clock's sounds (see `clock_core::Melody`).
+use embassy_rp::clocks::clk_sys_freq;
+use embassy_rp::pwm::{Channel, Config, Pwm};
+
+/// Divider of the system clock driving the PWM counter, leaving its period (`top`) in range for
+/// audible tones (e.g. 125 MHz / 64 is 1953 counts per cycle at 1 kHz).
+const DIVIDER: u8 = 64;
+
+/// Piezo buzzer on the B output of a PWM slice, sounding a square wave of a given frequency.
+pub struct Buzzer<'d, T: Channel> {
+    pwm: Pwm<'d, T>,
+    config: Config,
+}
+
+impl<'d, T: Channel> Buzzer<'d, T> {
+    /// Returns the buzzer (silent), driven by the given PWM slice.
+    pub fn new(mut pwm: Pwm<'d, T>) -> Self {
+        let mut config = Config::default();
+        config.divider = DIVIDER.into();
+        config.compare_b = 0;
+        pwm.set_config(&config);
+        Buzzer { pwm, config }
+    }
+
+    /// Sound a tone of the given frequency (in Hz), or fall silent if it is 0.
+    pub fn set_tone(&mut self, hz: u16) {
+        if hz == 0 {
+            self.config.compare_b = 0;
+        } else {
+            let counts = clk_sys_freq() / DIVIDER as u32 / hz as u32;
+            self.config.top = counts.clamp(2, u16::MAX as u32) as u16 - 1;
+            self.config.compare_b = (self.config.top + 1) / 2;
+        }
+        self.pwm.set_config(&self.config);
+    }
+}
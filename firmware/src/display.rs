@@ -0,0 +1,80 @@
+//! Display backends for the clock (see `clock_core::ClockDisplay`).
+use clock_core::I2cBus;
+use embassy_rp::i2c::{Blocking, Error, I2c, Instance};
+use embassy_time::{block_for, Duration};
+
+#[cfg(feature = "oled")]
+pub use oled::Oled;
+
+/// The I2C bus of the character LCD's backpack (see `clock_core::CharLcd`).
+pub struct LcdBus<'d, T: Instance>(pub I2c<'d, T, Blocking>);
+
+impl<T: Instance> I2cBus for LcdBus<'_, T> {
+    type Error = Error;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Error> {
+        self.0.blocking_write(address, bytes)
+    }
+
+    fn delay_micros(&mut self, micros: u32) {
+        block_for(Duration::from_micros(micros.into()));
+    }
+}
+
+#[cfg(feature = "oled")]
+mod oled {
+    use clock_core::display::graphics::draw_text;
+    use clock_core::ClockDisplay;
+    use display_interface::{DisplayError, WriteOnlyDataCommand};
+    use heapless::String;
+    use ssd1306::mode::BufferedGraphicsMode;
+    use ssd1306::prelude::Brightness;
+    use ssd1306::size::DisplaySize128x64;
+    use ssd1306::Ssd1306;
+
+    /// 128x64 OLED (SSD1306), showing players' times in a large font.
+    pub struct Oled<DI> {
+        display: Ssd1306<DI, DisplaySize128x64, BufferedGraphicsMode<DisplaySize128x64>>,
+        /// Text last shown: the frame is only redrawn (and sent over the bus) once it changes.
+        shown: Option<(String<32>, String<32>)>,
+    }
+
+    impl<DI> Oled<DI> {
+        /// Wraps the given (initialised) OLED, which is drawn on by the first `show`.
+        pub fn new(
+            display: Ssd1306<DI, DisplaySize128x64, BufferedGraphicsMode<DisplaySize128x64>>,
+        ) -> Self {
+            Oled {
+                display,
+                shown: None,
+            }
+        }
+    }
+
+    impl<DI: WriteOnlyDataCommand> ClockDisplay for Oled<DI> {
+        type Error = DisplayError;
+
+        fn show(&mut self, header: &str, status: &str) -> Result<(), DisplayError> {
+            if let Some((shown_header, shown_status)) = &self.shown {
+                if shown_header == header && shown_status == status {
+                    return Ok(());
+                }
+            }
+            self.shown = None;
+            draw_text(&mut self.display, header, status)?;
+            self.display.flush()?;
+            self.shown = Some((header.into(), status.into()));
+            Ok(())
+        }
+
+        /// Dims the OLED, which has no backlight.
+        fn set_backlight(&mut self, on: bool) -> Result<(), DisplayError> {
+            let brightness = if on {
+                Brightness::BRIGHTEST
+            } else {
+                Brightness::DIMMEST
+            };
+            self.display.set_brightness(brightness)
+        }
+    }
+}
@@ -0,0 +1,222 @@
+#![no_std]
+#![no_main]
+#![feature(type_alias_impl_trait)]
+
+use clock_core::{
+    Action, Button, ButtonEvent, ClockDisplay, Color, Event, Game, Melody, SettingsStore, Sound,
+};
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_futures::select::{select, Either};
+use embassy_rp::flash::Flash;
+use embassy_rp::gpio::{self, Pin};
+use embassy_rp::i2c::{self, Config};
+use embassy_rp::peripherals::PWM_CH7;
+use embassy_rp::pwm::{self, Pwm};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::{Channel, Receiver, Sender};
+use embassy_time::{Duration, Instant, Timer};
+use gpio::{AnyPin, Input, Level, Output, Pull};
+use {defmt_rtt as _, panic_probe as _};
+
+#[cfg(not(feature = "oled"))]
+use clock_core::{CharLcd, ShadowLcd};
+#[cfg(not(feature = "oled"))]
+use display::LcdBus;
+#[cfg(feature = "oled")]
+use display::Oled;
+#[cfg(feature = "oled")]
+use ssd1306::{prelude::*, I2CDisplayInterface, Ssd1306};
+
+mod buzzer;
+mod display;
+mod storage;
+
+use buzzer::Buzzer;
+use storage::{SettingsFlash, FLASH_BYTES};
+
+static CHANNEL: Channel<CriticalSectionRawMutex, ButtonEvent, 1> = Channel::new();
+static SOUNDS: Channel<CriticalSectionRawMutex, Sound, 2> = Channel::new();
+
+const DEBOUNCE_DELAY_MILLIS: u64 = 20;
+const TICK_MILLIS: u64 = 100;
+/// I2C bus speed: fast mode for the OLED, as each redraw sends it a whole frame (1 KiB);
+/// standard mode for the LCD's PCF8574 backpack, which supports no more.
+#[cfg(feature = "oled")]
+const I2C_HZ: u32 = 400_000;
+#[cfg(not(feature = "oled"))]
+const I2C_HZ: u32 = 100_000;
+
+/// Embassy task to monitor a given io port for user input.
+/// Sends a message using the given sender for the following events (see `clock_core::Button`):
+/// * button pressed (i.e. signal high again before the hold threshold; instantaneous)
+/// * button held (i.e. signal low; threshold set by const HOLD_MILLIS)
+/// * button hold repeated (every HOLD_MILLIS while still held)
+#[embassy_executor::task(pool_size = 3)]
+async fn button_watcher(
+    mut button: Input<'static, AnyPin>,
+    button_id: Color,
+    sender: Sender<'static, CriticalSectionRawMutex, ButtonEvent, 1>,
+) {
+    let mut state = Button::new(button_id);
+    loop {
+        button.wait_for_low().await;
+        Timer::after(Duration::from_millis(DEBOUNCE_DELAY_MILLIS)).await;
+        state.down(Instant::now().as_millis());
+
+        // monitor for continuous hold (repeated input) until released
+        while let Some(next_hold) = state.next_hold() {
+            let event = match select(
+                button.wait_for_high(),
+                Timer::at(Instant::from_millis(next_hold)),
+            )
+            .await
+            {
+                Either::First(()) => state.up(),
+                Either::Second(()) => state.poll(Instant::now().as_millis()),
+            };
+            if let Some(event) = event {
+                sender.send(event).await;
+            }
+        }
+        Timer::after(Duration::from_millis(DEBOUNCE_DELAY_MILLIS)).await;
+    }
+}
+
+/// Embassy task to play the sounds sent by the main loop on the buzzer, changing its tone as each
+/// note ends (see `clock_core::Melody`) so that the game carries on in the meantime.
+/// A new sound cuts short the one playing.
+#[embassy_executor::task]
+async fn sound_player(
+    mut buzzer: Buzzer<'static, PWM_CH7>,
+    receiver: Receiver<'static, CriticalSectionRawMutex, Sound, 2>,
+) {
+    let mut melody = Melody::new();
+    loop {
+        let sound = match melody.next_note() {
+            Some(next_note) => {
+                match select(
+                    receiver.receive(),
+                    Timer::at(Instant::from_millis(next_note)),
+                )
+                .await
+                {
+                    Either::First(sound) => Some(sound),
+                    Either::Second(()) => None,
+                }
+            }
+            None => Some(receiver.receive().await),
+        };
+        let now = Instant::now().as_millis();
+        let tone = match sound {
+            Some(sound) => Some(melody.play(sound, now)),
+            None => melody.poll(now),
+        };
+        if let Some(hz) = tone {
+            buzzer.set_tone(hz);
+        }
+    }
+}
+
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    let p = embassy_rp::init(Default::default());
+
+    //initialize IO
+    let mut red_led = Output::new(p.PIN_5.degrade(), Level::Low);
+    let mut yellow_led = Output::new(p.PIN_9.degrade(), Level::Low);
+    let mut blue_led = Output::new(p.PIN_13.degrade(), Level::Low);
+
+    let red_button = Input::new(p.PIN_6.degrade(), Pull::Up);
+    let yellow_button = Input::new(p.PIN_10.degrade(), Pull::Up);
+    let blue_button = Input::new(p.PIN_14.degrade(), Pull::Up);
+
+    // piezo buzzer on a spare pin (GPIO 15: output B of PWM slice 7)
+    let pwm = Pwm::new_output_b(p.PWM_CH7, p.PIN_15, pwm::Config::default());
+    let buzzer = Buzzer::new(pwm);
+
+    let mut i2c_config = Config::default();
+    i2c_config.frequency = I2C_HZ;
+    let i2c = i2c::I2c::new_blocking(p.I2C0, p.PIN_1, p.PIN_0, i2c_config);
+    #[cfg(not(feature = "oled"))]
+    let mut display = {
+        let lcd = CharLcd::new(LcdBus(i2c), 0x27).unwrap();
+        ShadowLcd::new(lcd)
+    };
+    #[cfg(feature = "oled")]
+    let mut display = {
+        let interface = I2CDisplayInterface::new(i2c);
+        let mut oled = Ssd1306::new(interface, DisplaySize128x64, DisplayRotation::Rotate0)
+            .into_buffered_graphics_mode();
+        oled.init().unwrap();
+        Oled::new(oled)
+    };
+
+    let sender = CHANNEL.sender();
+    let receiver = CHANNEL.receiver();
+
+    spawner
+        .spawn(button_watcher(red_button, Color::Red, sender.clone()))
+        .unwrap();
+    spawner
+        .spawn(button_watcher(yellow_button, Color::Yellow, sender.clone()))
+        .unwrap();
+    spawner
+        .spawn(button_watcher(blue_button, Color::Blue, sender.clone()))
+        .unwrap();
+    spawner
+        .spawn(sound_player(buzzer, SOUNDS.receiver()))
+        .unwrap();
+    let sounds = SOUNDS.sender();
+
+    // initiate game, with the settings of the last game played (if any)
+    let flash = Flash::<_, _, FLASH_BYTES>::new_blocking(p.FLASH);
+    let mut settings = SettingsStore::new(SettingsFlash(flash));
+    let saved = settings.load().unwrap_or_else(|_| {
+        warn!("failed to read settings");
+        None
+    });
+    let saved = saved.unwrap_or_default();
+    display.set_backlight(saved.backlight).unwrap();
+    let mut game = Game::with_settings(saved);
+    game.render(&mut display, Instant::now().as_millis())
+        .unwrap();
+
+    loop {
+        let event = match select(
+            receiver.receive(),
+            Timer::after(Duration::from_millis(TICK_MILLIS)),
+        )
+        .await
+        {
+            Either::First(button_event) => Event::Button(button_event),
+            Either::Second(()) => Event::Tick,
+        };
+        let now = Instant::now().as_millis();
+        for action in game.handle(event, now) {
+            match action {
+                Action::Led(color, on) => {
+                    let led = match color {
+                        Color::Red => &mut red_led,
+                        Color::Yellow => &mut yellow_led,
+                        Color::Blue => &mut blue_led,
+                    };
+                    led.set_level(if on { Level::High } else { Level::Low });
+                }
+                Action::Display => game.render(&mut display, now).unwrap(),
+                Action::SaveSettings => {
+                    if settings.save(game.saved_settings()).is_err() {
+                        warn!("failed to save settings");
+                    }
+                }
+                Action::Backlight(on) => display.set_backlight(on).unwrap(),
+                Action::Sound(sound) => {
+                    if sounds.try_send(sound).is_err() {
+                        warn!("sound dropped: {}", sound);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,30 @@
+//! Settings storage in the flash region reserved for it by `memory.x`
+//! (see `clock_core::SettingsStore`).
+use clock_core::{Flash, SECTOR_BYTES, STORAGE_BYTES};
+use embassy_rp::flash::{self, Blocking, Error};
+use embassy_rp::peripherals::FLASH;
+
+/// Size of the Pico's flash.
+pub const FLASH_BYTES: usize = 2048 * 1024;
+/// Offset of the settings region from the start of flash (the SETTINGS region in `memory.x`).
+const SETTINGS_OFFSET: u32 = FLASH_BYTES as u32 - STORAGE_BYTES;
+
+/// The flash region reserved for settings.
+pub struct SettingsFlash<'d>(pub flash::Flash<'d, FLASH, Blocking, FLASH_BYTES>);
+
+impl Flash for SettingsFlash<'_> {
+    type Error = Error;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Error> {
+        self.0.blocking_read(SETTINGS_OFFSET + offset, bytes)
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Error> {
+        self.0.blocking_write(SETTINGS_OFFSET + offset, bytes)
+    }
+
+    fn erase_sector(&mut self, offset: u32) -> Result<(), Error> {
+        let start = SETTINGS_OFFSET + offset;
+        self.0.blocking_erase(start, start + SECTOR_BYTES)
+    }
+}
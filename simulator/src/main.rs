@@ -0,0 +1,121 @@
+//! Desktop simulator: runs the clock logic in a terminal, with keyboard keys standing in for the
+//! Red/Yellow/Blue buttons and the 16x2 LCD drawn as a box (or the 128x64 OLED, with `--oled`).
+//!
+//! Keys: r/y/b press a button, R/Y/B hold it (Shift), q or Esc quits.
+use std::io::{self, Stdout, Write};
+use std::time::{Duration, Instant};
+
+use clock_core::{Action, ButtonEvent, Color, Event, FrameBuffer, Game, Sound};
+use crossterm::event::{self, Event as TermEvent, KeyCode, KeyEventKind};
+use crossterm::{cursor, execute, queue, style, terminal};
+use panel::{Lcd, Oled, Panel};
+
+mod panel;
+
+const TICK_MILLIS: u64 = 100;
+
+fn main() -> io::Result<()> {
+    let mut panel: Box<dyn Panel> = if std::env::args().any(|arg| arg == "--oled") {
+        Box::new(Oled::new())
+    } else {
+        Box::new(Lcd(FrameBuffer::new()))
+    };
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+    let result = run(&mut stdout, panel.as_mut());
+    execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+/// Feeds key presses and timer ticks into the game until the user quits.
+fn run(stdout: &mut Stdout, panel: &mut dyn Panel) -> io::Result<()> {
+    let start = Instant::now();
+    let mut game = Game::new();
+    let mut leds = [false; 3];
+    let mut next_tick = start + Duration::from_millis(TICK_MILLIS);
+    let Ok(()) = game.render(panel, 0);
+    draw(stdout, panel, leds)?;
+    loop {
+        let timeout = next_tick.saturating_duration_since(Instant::now());
+        let event = if event::poll(timeout)? {
+            match event::read()? {
+                TermEvent::Key(key) if key.kind == KeyEventKind::Press => match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    code => match button_event(code) {
+                        Some(button_event) => Event::Button(button_event),
+                        None => continue,
+                    },
+                },
+                _ => continue,
+            }
+        } else {
+            next_tick += Duration::from_millis(TICK_MILLIS);
+            Event::Tick
+        };
+
+        let now = start.elapsed().as_millis() as u64;
+        let actions = game.handle(event, now);
+        for action in &actions {
+            match *action {
+                Action::Led(color, on) => leds[led_index(color)] = on,
+                Action::Display => {
+                    let Ok(()) = game.render(panel, now);
+                }
+                // settings only last as long as the simulator
+                Action::SaveSettings => (),
+                // the terminal has no backlight to switch
+                Action::Backlight(_) => (),
+                // the terminal bell stands in for the buzzer, except for clicks
+                Action::Sound(Sound::Click) => (),
+                Action::Sound(_) => execute!(stdout, style::Print('\x07'))?,
+            }
+        }
+        if !actions.is_empty() {
+            draw(stdout, panel, leds)?;
+        }
+    }
+}
+
+/// Returns the button event simulated by the given key, if any.
+/// Lowercase keys press a button; uppercase keys hold it.
+fn button_event(code: KeyCode) -> Option<ButtonEvent> {
+    let KeyCode::Char(key) = code else {
+        return None;
+    };
+    let color = match key.to_ascii_lowercase() {
+        'r' => Color::Red,
+        'y' => Color::Yellow,
+        'b' => Color::Blue,
+        _ => return None,
+    };
+    if key.is_ascii_uppercase() {
+        Some(ButtonEvent::Held(color))
+    } else {
+        Some(ButtonEvent::Pressed(color))
+    }
+}
+
+fn led_index(color: Color) -> usize {
+    match color {
+        Color::Red => 0,
+        Color::Yellow => 1,
+        Color::Blue => 2,
+    }
+}
+
+/// Draws the display, with the LEDs and key bindings underneath.
+fn draw(stdout: &mut Stdout, panel: &dyn Panel, leds: [bool; 3]) -> io::Result<()> {
+    let mut lines = panel.lines();
+    let led = |index: usize| if leds[index] { '●' } else { '○' };
+    lines.push(format!(" Red {}  {}  {} Blue", led(0), led(1), led(2)));
+    lines.push(String::new());
+    lines.push("r/y/b: press  R/Y/B: hold  q: quit".to_string());
+
+    queue!(stdout, terminal::Clear(terminal::ClearType::All))?;
+    for (y, line) in lines.iter().enumerate() {
+        queue!(stdout, cursor::MoveTo(0, y as u16), style::Print(line))?;
+    }
+    stdout.flush()
+}
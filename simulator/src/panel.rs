@@ -0,0 +1,101 @@
+//! Simulated displays, drawn in the terminal as lines of text.
+use std::convert::Infallible;
+
+use clock_core::display::graphics::draw_text;
+use clock_core::{ClockDisplay, FrameBuffer, DISPLAY_COLUMNS};
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::*;
+
+const OLED_WIDTH: usize = 128;
+const OLED_HEIGHT: usize = 64;
+
+/// A display which can be drawn in the terminal.
+pub trait Panel: ClockDisplay<Error = Infallible> {
+    /// Returns the display's contents as lines of text, including its border.
+    fn lines(&self) -> Vec<String>;
+}
+
+/// 16x2 character LCD, drawn as a box.
+pub struct Lcd(pub FrameBuffer);
+
+impl ClockDisplay for Lcd {
+    type Error = Infallible;
+
+    fn show(&mut self, header: &str, status: &str) -> Result<(), Infallible> {
+        self.0.show(header, status)
+    }
+}
+
+impl Panel for Lcd {
+    fn lines(&self) -> Vec<String> {
+        let border = "─".repeat(DISPLAY_COLUMNS);
+        vec![
+            format!("┌{border}┐"),
+            format!("│{}│", self.0.row(0)),
+            format!("│{}│", self.0.row(1)),
+            format!("└{border}┘"),
+        ]
+    }
+}
+
+/// 128x64 OLED, drawn two pixel rows per line using half block characters.
+pub struct Oled(Box<[[bool; OLED_WIDTH]; OLED_HEIGHT]>);
+
+impl Oled {
+    pub fn new() -> Oled {
+        Oled(Box::new([[false; OLED_WIDTH]; OLED_HEIGHT]))
+    }
+}
+
+impl OriginDimensions for Oled {
+    fn size(&self) -> Size {
+        Size::new(OLED_WIDTH as u32, OLED_HEIGHT as u32)
+    }
+}
+
+impl DrawTarget for Oled {
+    type Color = BinaryColor;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Infallible>
+    where
+        I: IntoIterator<Item = Pixel<BinaryColor>>,
+    {
+        for Pixel(point, color) in pixels {
+            if let (Ok(x), Ok(y)) = (usize::try_from(point.x), usize::try_from(point.y)) {
+                if x < OLED_WIDTH && y < OLED_HEIGHT {
+                    self.0[y][x] = color.is_on();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ClockDisplay for Oled {
+    type Error = Infallible;
+
+    fn show(&mut self, header: &str, status: &str) -> Result<(), Infallible> {
+        draw_text(self, header, status)
+    }
+}
+
+impl Panel for Oled {
+    fn lines(&self) -> Vec<String> {
+        let border = "─".repeat(OLED_WIDTH);
+        let mut lines = vec![format!("┌{border}┐")];
+        for rows in self.0.chunks(2) {
+            let line: String = (0..OLED_WIDTH)
+                .map(|x| match (rows[0][x], rows[1][x]) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                })
+                .collect();
+            lines.push(format!("│{line}│"));
+        }
+        lines.push(format!("└{border}┘"));
+        lines
+    }
+}